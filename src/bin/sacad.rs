@@ -1,17 +1,53 @@
-//! placeholder
+//! Search and download a single album cover, or recursively scan a library (`scan` subcommand)
+
+use std::{process::ExitCode, sync::Arc, time::Duration};
 
 use anyhow::Context as _;
 use clap::Parser as _;
-use sacad::{cl, search_and_download};
+use sacad::{
+    SearchCache, SearchStatus, SourceClients,
+    cl::{self, Command, SearchQuery},
+    search_and_download,
+};
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     // Parse CL args
     let cl_args = cl::Args::parse();
 
-    // Init logger
-    simple_logger::init_with_level(cl_args.verbosity).context("Failed to setup logger")?;
+    match cl_args.command {
+        Some(Command::Scan(scan_args)) => {
+            simple_logger::init_with_level(scan_args.verbosity).context("Failed to setup logger")?;
+            sacad::recursive::run(scan_args).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        None => {
+            simple_logger::init_with_level(cl_args.verbosity).context("Failed to setup logger")?;
+
+            let query = SearchQuery {
+                artist: cl_args.search.artist,
+                album: cl_args.search.album,
+                release_mbid: None,
+            };
+            let cache = Arc::new(SearchCache::new(Duration::from_secs(
+                cl_args.search.options.cache_ttl_secs,
+            )));
+            let source_clients = SourceClients::build(&cl_args.search.options)
+                .context("Failed to initialize sources")?;
+            let status = search_and_download(
+                &cl_args.image_output.output_filepath,
+                Arc::new(query),
+                Arc::new(cl_args.search.options),
+                &cl_args.image_output.processing,
+                &cache,
+                &source_clients,
+            )
+            .await?;
 
-    // Run
-    search_and_download(cl_args.search, cl_args.image_output).await
+            Ok(match status {
+                SearchStatus::Found => ExitCode::SUCCESS,
+                SearchStatus::NotFound => ExitCode::FAILURE,
+            })
+        }
+    }
 }
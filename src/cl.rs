@@ -2,13 +2,16 @@
 
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use strum::VariantArray as _;
 
 /// Command line arguments
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Args {
+    /// Recursively scan a library instead of searching a single artist/album (see `Command::Scan`)
+    #[clap(subcommand)]
+    pub command: Option<Command>,
     /// Search options
     #[clap(flatten)]
     pub search: SearchArgs,
@@ -20,6 +23,15 @@ pub struct Args {
     pub verbosity: log::Level,
 }
 
+/// Alternative modes to the default single artist/album search
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Recursively scan a music library, grouping audio files by album, and fetch covers for
+    /// every album missing one. This is the same engine as the standalone `sacad-recursive`
+    /// binary
+    Scan(SacadRecursiveArgs),
+}
+
 /// Command line arguments related to search
 #[derive(Parser, Debug)]
 pub struct SearchArgs {
@@ -27,41 +39,226 @@ pub struct SearchArgs {
     pub artist: String,
     /// Album to search for
     pub album: String,
+    /// Search options shared with `sacad-recursive`
+    #[clap(flatten)]
+    pub options: SearchOptions,
+}
+
+/// Search options shared between `sacad` and `sacad-recursive`
+#[derive(Parser, Debug)]
+pub struct SearchOptions {
     /// Target image size
     pub size: u32,
     /// Tolerate this percentage of size difference with the target size.
     /// Note that covers with size above or close to the target size will still be preferred if available
     #[clap(short = 't', long = "size-tolerance", default_value_t = 25)]
     pub size_tolerance_prct: u32,
-    /// Cover sources to use, if not set use all of them.
+    /// Cover sources to use, if not set use all of them except `subsonic` (which requires
+    /// server configuration to be usable).
     /// This option should either be the last one in the command line, or be passed immediately before positional
     /// arguments and followed by '--' (ie. `sacad -s source1 source2 -- artist album size out_filepath`)
-    #[clap(short = 's', long, default_values_t = CoverSourceName::VARIANTS.to_vec())]
-    pub cover_sources: Vec<CoverSourceName>,
+    #[clap(short = 's', long, default_values_t = SourceName::default_sources())]
+    pub cover_sources: Vec<SourceName>,
+    /// How long search results from a source are cached for, in seconds, before being refreshed.
+    /// Only matters when the same artist/album is searched for more than once, eg. for a multi-disc
+    /// album in a recursive scan
+    #[clap(long, default_value_t = 3600)]
+    pub cache_ttl_secs: u64,
+    /// How to use the target `size` to pick the best cover among search results
+    #[clap(long, default_value_t = CoverPreset::ClosestTo)]
+    pub cover_preset: CoverPreset,
+    /// Base URL of a Subsonic-compatible server (Navidrome/Airsonic/Gonic), required when the
+    /// `subsonic` source is enabled
+    #[clap(long)]
+    pub subsonic_url: Option<String>,
+    /// Login username for `subsonic_url`
+    #[clap(long)]
+    pub subsonic_user: Option<String>,
+    /// Login password for `subsonic_url`
+    #[clap(long)]
+    pub subsonic_password: Option<String>,
+}
+
+/// Preset controlling how the target `size` is used to select a cover among search results
+#[derive(
+    Debug, Clone, Copy, strum::EnumString, strum::VariantArray, strum::AsRefStr, strum::Display,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum CoverPreset {
+    /// Prefer the largest image available, ignoring `size`
+    Largest,
+    /// Prefer the smallest image that is at least as large as `size`, discarding smaller ones
+    AtLeast,
+    /// Prefer the image whose size is closest to `size` (default)
+    ClosestTo,
+    /// Prefer the best quality format (eg. lossless over lossy); `size` only breaks ties
+    BestQualityFormat,
+}
+
+/// A single artist/album pair to search a cover for
+#[derive(Debug)]
+pub struct SearchQuery {
+    /// Artist to search for
+    pub artist: String,
+    /// Album to search for
+    pub album: String,
+    /// `MusicBrainz` release MBID, if already known (eg. from tags), to allow sources to skip
+    /// fuzzy text search
+    pub release_mbid: Option<String>,
 }
 
 /// Command line arguments related to output image processing
 #[derive(Parser, Debug)]
 pub struct ImageOutputArgs {
     /// Output image file path
-    output_filepath: PathBuf,
-    /// Preserve source image format if possible.
+    pub(crate) output_filepath: PathBuf,
+    /// Image processing options shared with `sacad-recursive`
+    #[clap(flatten)]
+    pub(crate) processing: ImageProcessingArgs,
+}
+
+/// Image processing options shared between `sacad` and `sacad-recursive`
+#[derive(Parser, Debug)]
+pub struct ImageProcessingArgs {
+    /// Preserve source image format if possible, skipping re-encoding entirely.
     /// Target format will still be prefered when sorting results
     #[clap(short, long)]
-    preserve_format: bool,
+    pub(crate) preserve_format: bool,
     /// Convert progressive JPEG to baseline if needed.
     /// May result in bigger files and loss of quality
     #[clap(long)]
-    convert_progressive_jpeg: bool,
+    pub(crate) convert_progressive_jpeg: bool,
+    /// Ordered list of acceptable output formats. A cover already in an earlier-listed format is
+    /// preferred when sorting results. A cover in none of these formats is re-encoded to the
+    /// first one, unless `preserve_format` is set; a cover in any of them is kept as is.
+    /// This option should either be the last one in the command line, or be passed immediately before positional
+    /// arguments and followed by '--'
+    #[clap(long, default_values_t = OutputFormat::VARIANTS.to_vec())]
+    pub(crate) format: Vec<OutputFormat>,
+    /// Re-encoding quality (1-100) used when converting to a lossy format such as JPEG
+    #[clap(long, default_value_t = 90)]
+    pub(crate) quality: u8,
+}
+
+/// An acceptable output image format, as selected by `--format`
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, strum::EnumString, strum::VariantArray, strum::AsRefStr, strum::Display,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    /// JPEG
+    Jpeg,
+    /// PNG
+    Png,
 }
 
 /// Cover source name
-#[derive(Debug, Clone, strum::EnumString, strum::VariantArray, strum::AsRefStr, strum::Display)]
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    strum::EnumString,
+    strum::VariantArray,
+    strum::AsRefStr,
+    strum::Display,
+)]
 #[strum(serialize_all = "lowercase")]
 #[expect(missing_docs)]
-pub enum CoverSourceName {
+pub enum SourceName {
+    CoverArtArchive,
     Deezer,
     Discogs,
     Itunes,
     LastFm,
+    Subsonic,
 }
+
+impl SourceName {
+    /// Sources enabled by default when `-s`/`--cover-sources` is not specified. `Subsonic` is
+    /// excluded since it requires server configuration to be usable
+    fn default_sources() -> Vec<Self> {
+        Self::VARIANTS
+            .iter()
+            .filter(|s| !matches!(s, Self::Subsonic))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Command line arguments for the `sacad-recursive` binary
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct SacadRecursiveArgs {
+    /// Root directory of the music library to recursively scan.
+    /// Not used, and may be omitted, when `--subsonic-scan` is set
+    pub lib_root_dir: Option<PathBuf>,
+    /// Scan a remote Subsonic-compatible server's library (via `getAlbumList2`) instead of
+    /// `lib_root_dir`. Requires `--subsonic-url`/`--subsonic-user`/`--subsonic-password` and is
+    /// incompatible with `--embed`, since there are no local files to embed into
+    #[clap(long)]
+    pub subsonic_scan: bool,
+    /// Search options
+    #[clap(flatten)]
+    pub search_opts: SearchOptions,
+    /// Image processing options
+    #[clap(flatten)]
+    pub image_proc: ImageProcessingArgs,
+    /// Where to write found covers
+    #[clap(flatten)]
+    output: CoverOutputRawArgs,
+    /// Search and overwrite covers for albums that already have one
+    #[clap(long)]
+    pub ignore_existing: bool,
+    /// Number of threads reading audio file tags off disk, defaults to available parallelism
+    #[clap(long)]
+    pub reader_threads: Option<usize>,
+    /// Number of concurrent cover search & download workers
+    #[clap(long, default_value_t = 8)]
+    pub workers: usize,
+    /// Level of logging output
+    #[clap(short, long, default_value_t = log::Level::Info)]
+    pub verbosity: log::Level,
+}
+
+impl SacadRecursiveArgs {
+    /// Resolve the raw CLI output flags into a `CoverOutput`
+    #[must_use]
+    pub fn output(&self) -> CoverOutput {
+        if self.output.embed {
+            CoverOutput::Embed
+        } else {
+            CoverOutput::Pattern(CoverOutputPattern(
+                self.output
+                    .output_pattern
+                    .clone()
+                    .unwrap_or_else(|| "{artist} - {album}/cover.jpg".to_owned()),
+            ))
+        }
+    }
+}
+
+/// Raw, mutually exclusive CLI flags selecting how to output covers
+#[derive(Parser, Debug)]
+struct CoverOutputRawArgs {
+    /// Embed the cover directly into the audio files' tags
+    #[clap(long, conflicts_with = "output_pattern")]
+    embed: bool,
+    /// Write the cover to a file, using `{artist}`/`{album}` placeholders
+    #[clap(long)]
+    output_pattern: Option<String>,
+}
+
+/// Where to output a found cover
+#[derive(Debug, Clone)]
+pub enum CoverOutput {
+    /// Embed into tags for given files
+    Embed,
+    /// Write to a file path built from a pattern
+    Pattern(CoverOutputPattern<String>),
+}
+
+/// A file path pattern with `{artist}`/`{album}` placeholders
+#[derive(Debug, Clone)]
+pub struct CoverOutputPattern<S>(pub S);
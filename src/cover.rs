@@ -1,29 +1,325 @@
 //! Cover
 
-use std::fmt;
+use std::{fmt, fs, io::Cursor, path::Path, sync::Arc};
 
-use crate::cl::{ImageOutputArgs, SearchArgs};
+use anyhow::Context as _;
+
+#[cfg(any(
+    feature = "ahash",
+    feature = "dhash",
+    feature = "blockhash",
+    feature = "phash"
+))]
+use crate::perceptual_hash::PerceptualHash;
+use crate::{
+    cl::{self, CoverPreset, ImageProcessingArgs, SearchOptions, SourceName},
+    http::SourceHttpClient,
+    source::Relevance,
+};
+
+/// Image format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    /// JPEG
+    Jpeg,
+    /// PNG
+    Png,
+}
+
+impl Format {
+    /// Guess format from a file extension (without the leading dot)
+    pub(crate) fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    /// Map an `image` crate format to our own, if we can re-encode to/from it
+    fn from_image_format(format: image::ImageFormat) -> Option<Self> {
+        match format {
+            image::ImageFormat::Jpeg => Some(Self::Jpeg),
+            image::ImageFormat::Png => Some(Self::Png),
+            _ => None,
+        }
+    }
+}
+
+impl From<cl::OutputFormat> for Format {
+    fn from(value: cl::OutputFormat) -> Self {
+        match value {
+            cl::OutputFormat::Jpeg => Self::Jpeg,
+            cl::OutputFormat::Png => Self::Png,
+        }
+    }
+}
+
+/// A value reported by a source, which may be an exact fact or a best-effort guess
+#[derive(Debug, Clone)]
+pub(crate) struct Metadata<T> {
+    /// The value itself
+    value: T,
+    /// Whether `value` is guaranteed accurate, or just a hint
+    certain: bool,
+}
+
+impl<T> Metadata<T> {
+    /// Build a value the source is certain about
+    pub(crate) fn known(value: T) -> Self {
+        Self {
+            value,
+            certain: true,
+        }
+    }
+
+    /// Build a value the source could only guess at
+    pub(crate) fn uncertain(value: T) -> Self {
+        Self {
+            value,
+            certain: false,
+        }
+    }
+
+    /// Get the value, whether it is certain or just a hint
+    pub(crate) fn value_hint(&self) -> &T {
+        &self.value
+    }
+
+    /// Whether the value is guaranteed accurate
+    pub(crate) fn is_certain(&self) -> bool {
+        self.certain
+    }
+}
 
 /// A cover result
+#[derive(Clone)]
 pub(crate) struct Cover {
     /// The main cover image URL
-    url: reqwest::Url,
+    pub(crate) url: reqwest::Url,
+    /// A smaller preview image URL
+    pub(crate) thumbnail_url: reqwest::Url,
+    /// Image dimensions, if known or guessed
+    pub(crate) size_px: Metadata<(u32, u32)>,
+    /// Image format, if known or guessed
+    pub(crate) format: Metadata<Format>,
+    /// Source that returned this cover
+    pub(crate) source_name: SourceName,
+    /// HTTP client to use to fetch this cover
+    pub(crate) source_http: Arc<SourceHttpClient>,
+    /// How relevant/trustworthy this result is
+    pub(crate) relevance: Relevance,
+    /// Rank among results returned by the same source, lower is better
+    pub(crate) rank: usize,
 }
 
 impl fmt::Display for Cover {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.url, self.source_name)
     }
 }
 
 impl Cover {
-    /// Download cover to local file
-    pub(crate) fn download(&self, _output: &ImageOutputArgs) -> anyhow::Result<()> {
-        todo!()
+    /// Download cover to local file, re-encoding it to the preferred output format unless
+    /// `preserve_format` is set
+    pub(crate) async fn download(
+        &self,
+        output_filepath: &Path,
+        image_proc: &ImageProcessingArgs,
+    ) -> anyhow::Result<()> {
+        let data = self
+            .source_http
+            .get_bytes(self.url.clone())
+            .await
+            .with_context(|| format!("Failed to download {self}"))?;
+        let data = if image_proc.preserve_format {
+            data.to_vec()
+        } else {
+            reencode(&data, &image_proc.format, image_proc.quality)
+                .with_context(|| format!("Failed to re-encode {self}"))?
+        };
+        fs::write(output_filepath, &data)
+            .with_context(|| format!("Failed to write {output_filepath:?}"))?;
+        Ok(())
     }
 }
 
-/// Sort covers, with most relevant first
-pub(crate) fn sort(_results: &mut Vec<Cover>, _search: &SearchArgs) {
-    todo!()
+/// Re-encode `data` to the first format in `preference`, using `quality` for lossy targets.
+/// Falls back to the original bytes unchanged if `preference` is empty, the source format can't
+/// be identified or isn't JPEG/PNG, or it is already acceptable (anywhere in `preference`, not
+/// just first). A failure to decode or re-encode the image is returned as an error
+fn reencode(data: &[u8], preference: &[cl::OutputFormat], quality: u8) -> anyhow::Result<Vec<u8>> {
+    let Some(&target) = preference.first() else {
+        return Ok(data.to_vec());
+    };
+    let Ok(image_source_format) = image::guess_format(data) else {
+        return Ok(data.to_vec());
+    };
+    let Some(source_format) = Format::from_image_format(image_source_format) else {
+        return Ok(data.to_vec());
+    };
+    if preference
+        .iter()
+        .any(|&format| Format::from(format) == source_format)
+    {
+        return Ok(data.to_vec());
+    }
+
+    let img = image::load_from_memory_with_format(data, image_source_format)
+        .context("Failed to decode downloaded image")?;
+    let mut out = Vec::new();
+    match target {
+        cl::OutputFormat::Jpeg => {
+            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality);
+            encoder
+                .encode_image(&img)
+                .context("Failed to encode JPEG")?;
+        }
+        cl::OutputFormat::Png => {
+            img.write_to(&mut Cursor::new(&mut out), image::ImageFormat::Png)
+                .context("Failed to encode PNG")?;
+        }
+    }
+    Ok(out)
+}
+
+/// Remove cross-source duplicate covers (the same artwork returned by more than one source),
+/// keeping the most relevant copy of each perceptually-similar cluster. Requires one of the
+/// `ahash`/`dhash`/`blockhash`/`phash` cargo features; a no-op otherwise
+#[cfg(any(
+    feature = "ahash",
+    feature = "dhash",
+    feature = "blockhash",
+    feature = "phash"
+))]
+pub(crate) async fn dedup_by_perceptual_hash(results: &mut Vec<Cover>) {
+    let mut hashes = Vec::with_capacity(results.len());
+    for cover in results.iter() {
+        let hash = match cover
+            .source_http
+            .get_bytes(cover.thumbnail_url.clone())
+            .await
+        {
+            Ok(data) => PerceptualHash::from_image_buffer(&data)
+                .inspect_err(|err| log::warn!("Failed to hash thumbnail for {cover}: {err:#}"))
+                .ok(),
+            Err(err) => {
+                log::warn!("Failed to fetch thumbnail for {cover}: {err:#}");
+                None
+            }
+        };
+        hashes.push(hash);
+    }
+
+    let mut keep = vec![true; results.len()];
+    for i in 0..results.len() {
+        if !keep[i] {
+            continue;
+        }
+        let Some(hash_i) = &hashes[i] else { continue };
+        for j in (i + 1)..results.len() {
+            if !keep[j] || results[i].source_name == results[j].source_name {
+                continue;
+            }
+            let Some(hash_j) = &hashes[j] else { continue };
+            if !hash_i.is_similar(hash_j) {
+                continue;
+            }
+            // Drop the less relevant of the pair, keeping whichever source is more trustworthy
+            if results[j].relevance.score > results[i].relevance.score {
+                keep[i] = false;
+                break;
+            }
+            keep[j] = false;
+        }
+    }
+
+    let mut keep = keep.into_iter();
+    results.retain(|_| keep.next().unwrap_or(true));
+}
+#[cfg(not(any(
+    feature = "ahash",
+    feature = "dhash",
+    feature = "blockhash",
+    feature = "phash"
+)))]
+pub(crate) async fn dedup_by_perceptual_hash(_results: &mut Vec<Cover>) {}
+
+/// Sort covers, with most relevant first, honoring the configured `CoverPreset`. Covers already
+/// in a format from `image_proc`'s preference list sort ahead of ones that would need conversion,
+/// except under `CoverPreset::BestQualityFormat`, which uses its own format order instead
+pub(crate) fn sort(
+    results: &mut Vec<Cover>,
+    search_opts: &SearchOptions,
+    image_proc: &ImageProcessingArgs,
+) {
+    results.retain(|cover| cover.relevance.score >= crate::source::MIN_MATCH_SCORE);
+    if matches!(search_opts.cover_preset, CoverPreset::AtLeast) {
+        results.retain(|cover| width(cover) >= search_opts.size);
+    }
+
+    results.sort_by(|a, b| {
+        b.relevance
+            .score
+            .cmp(&a.relevance.score)
+            // A source at risk of returning art unrelated to the release is less trustworthy
+            // than one that isn't, regardless of how well the text matched
+            .then_with(|| a.relevance.unrelated_risk.cmp(&b.relevance.unrelated_risk))
+            // A source guaranteed to only return front covers is preferred over one that may
+            // also return back/inlay art (eg. Discogs)
+            .then_with(|| {
+                b.relevance
+                    .only_front_covers
+                    .cmp(&a.relevance.only_front_covers)
+            })
+            // BestQualityFormat picks its own format ordering below (eg. PNG over JPEG); applying
+            // the --format preference list here first would shadow it under the default list,
+            // which prefers JPEG
+            .then_with(|| {
+                if matches!(search_opts.cover_preset, CoverPreset::BestQualityFormat) {
+                    std::cmp::Ordering::Equal
+                } else {
+                    format_preference_rank(a, image_proc).cmp(&format_preference_rank(b, image_proc))
+                }
+            })
+            .then_with(|| match search_opts.cover_preset {
+                CoverPreset::Largest => width(b).cmp(&width(a)),
+                CoverPreset::AtLeast => width(a).cmp(&width(b)),
+                CoverPreset::ClosestTo => size_delta(a, search_opts).cmp(&size_delta(b, search_opts)),
+                CoverPreset::BestQualityFormat => format_rank(a).cmp(&format_rank(b)),
+            })
+            .then_with(|| size_delta(a, search_opts).cmp(&size_delta(b, search_opts)))
+            // Prefer a cover whose size is actually known over one where it's only guessed
+            .then_with(|| b.size_px.is_certain().cmp(&a.size_px.is_certain()))
+            .then_with(|| a.rank.cmp(&b.rank))
+    });
+}
+
+/// Position of a cover's (hinted) format in the user's output format preference list, lower is
+/// better; formats not in the list sort last
+fn format_preference_rank(cover: &Cover, image_proc: &ImageProcessingArgs) -> usize {
+    image_proc
+        .format
+        .iter()
+        .position(|&f| Format::from(f) == *cover.format.value_hint())
+        .unwrap_or(image_proc.format.len())
+}
+
+/// A cover's (hinted) width in pixels
+fn width(cover: &Cover) -> u32 {
+    let (width, _height) = *cover.size_px.value_hint();
+    width
+}
+
+/// Absolute distance in pixels between a cover's (hinted) width and the searched target size
+fn size_delta(cover: &Cover, search_opts: &SearchOptions) -> u32 {
+    width(cover).abs_diff(search_opts.size)
+}
+
+/// Lower is better: preference ranking used by `CoverPreset::BestQualityFormat`
+fn format_rank(cover: &Cover) -> u8 {
+    match cover.format.value_hint() {
+        Format::Png => 0,
+        Format::Jpeg => 1,
+    }
 }
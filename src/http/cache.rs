@@ -2,12 +2,17 @@
 
 #![expect(clippy::result_large_err)]
 
-use std::path::Path;
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 /// On-disk key-value cache
 pub(super) struct Cache {
     /// Inner database
     db: redb::Database,
+    /// Entries older than this are treated as a miss, and lazily/eagerly evicted
+    max_age: Duration,
 }
 
 /// Error the cache can return
@@ -18,6 +23,8 @@ pub(crate) enum CacheError {
     Bitcode(#[from] bitcode::Error),
     #[error("Database commit error: {0}")]
     Commit(#[from] redb::CommitError),
+    #[error("Database compaction error: {0}")]
+    Compaction(#[from] redb::CompactionError),
     #[error("Database error: {0}")]
     Database(#[from] redb::DatabaseError),
     #[error("Decompression error: {0}")]
@@ -30,22 +37,51 @@ pub(crate) enum CacheError {
     Transaction(#[from] redb::TransactionError),
 }
 
-/// redb table for cache
-const REDB_TABLE: redb::TableDefinition<&str, Vec<u8>> = redb::TableDefinition::new("cache_v1");
+/// redb table for cache. Bumped from `cache_v1` so that records written by older binaries, which
+/// have no `stored_at`, are simply invisible rather than misparsed
+const REDB_TABLE: redb::TableDefinition<&str, Vec<u8>> = redb::TableDefinition::new("cache_v2");
+
+/// On-disk representation of a cached value, wrapped with the time it was stored so it can be
+/// evicted once stale
+#[derive(bitcode::Encode, bitcode::Decode)]
+struct Record {
+    /// Unix timestamp (seconds) the record was stored at
+    stored_at: u64,
+    /// Bitcode-encoded value
+    payload: Vec<u8>,
+}
+
+/// Current unix timestamp in seconds, saturating to 0 on a clock before the epoch
+fn now_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Decompress and decode a raw stored value into a `Record`. Returns an error both on actual
+/// corruption and on records written in an older, incompatible format; callers should treat
+/// either case as a cache miss
+fn decode_record(raw: &[u8]) -> Result<Record, CacheError> {
+    let encoded = lz4_flex::decompress_size_prepended(raw)?;
+    Ok(bitcode::decode(&encoded)?)
+}
 
 impl Cache {
-    /// Create a new cache instance
-    pub(crate) fn new<P>(path: P) -> Result<Self, CacheError>
+    /// Create a new cache instance, evicting any entries already older than `max_age`
+    pub(crate) fn new<P>(path: P, max_age: Duration) -> Result<Self, CacheError>
     where
         P: AsRef<Path>,
     {
-        // TODO periodic scan all to evict old entries + compact
-        Ok(Self {
+        let mut cache = Self {
             db: redb::Database::create(path)?,
-        })
+            max_age,
+        };
+        cache.evict(max_age)?;
+        Ok(cache)
     }
 
-    /// Get a single value from cache
+    /// Get a single value from cache. Entries older than `max_age` (passed to `new`) are treated
+    /// as a miss and lazily deleted
     pub(crate) fn get<K, V>(&self, key: K) -> Result<Option<V>, CacheError>
     where
         K: AsRef<str>,
@@ -57,13 +93,20 @@ impl Cache {
             Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
             Err(err) => return Err(err.into()),
         };
-        if let Some(raw_value) = table.get(key.as_ref())? {
-            let encoded = lz4_flex::decompress_size_prepended(&raw_value.value())?;
-            let value: V = bitcode::decode(&encoded)?;
-            Ok(Some(value))
-        } else {
-            Ok(None)
+        let Some(raw_value) = table.get(key.as_ref())? else {
+            return Ok(None);
+        };
+        let Ok(record) = decode_record(&raw_value.value()) else {
+            return Ok(None);
+        };
+        drop(table);
+        drop(db_read);
+        if now_unix_secs().saturating_sub(record.stored_at) >= self.max_age.as_secs() {
+            self.delete(key.as_ref())?;
+            return Ok(None);
         }
+        let value: V = bitcode::decode(&record.payload)?;
+        Ok(Some(value))
     }
 
     /// Set single value in cache
@@ -81,12 +124,14 @@ impl Cache {
         K: AsRef<str>,
         V: bitcode::Encode,
     {
+        let stored_at = now_unix_secs();
         let db_write = self.db.begin_write()?;
         {
             let mut table = db_write.open_table(REDB_TABLE)?;
             #[expect(clippy::cast_precision_loss)]
             for (k, v) in kvs {
-                let encoded = bitcode::encode(*v);
+                let payload = bitcode::encode(*v);
+                let encoded = bitcode::encode(&Record { stored_at, payload });
                 let compressed = lz4_flex::compress_prepend_size(&encoded);
                 log::debug!(
                     "Data for cache key {} compression ratio: {:.2}%",
@@ -99,6 +144,54 @@ impl Cache {
         db_write.commit()?;
         Ok(())
     }
+
+    /// Delete a single key in its own short write transaction
+    fn delete(&self, key: &str) -> Result<(), CacheError> {
+        let db_write = self.db.begin_write()?;
+        {
+            let mut table = db_write.open_table(REDB_TABLE)?;
+            table.remove(key)?;
+        }
+        db_write.commit()?;
+        Ok(())
+    }
+
+    /// Scan every entry, delete those older than `max_age`, then compact the database to reclaim
+    /// the freed space. Safe to run concurrently with `get`/`set`
+    pub(crate) fn evict(&mut self, max_age: Duration) -> Result<(), CacheError> {
+        let now = now_unix_secs();
+        let expired_keys = {
+            let db_read = self.db.begin_read()?;
+            let table = match db_read.open_table(REDB_TABLE) {
+                Ok(table) => table,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(()),
+                Err(err) => return Err(err.into()),
+            };
+            table
+                .iter()?
+                .filter_map(|entry| {
+                    let (key, raw_value) = entry.ok()?;
+                    let stored_at = decode_record(&raw_value.value()).ok()?.stored_at;
+                    (now.saturating_sub(stored_at) >= max_age.as_secs())
+                        .then(|| key.value().to_owned())
+                })
+                .collect::<Vec<_>>()
+        };
+        if expired_keys.is_empty() {
+            return Ok(());
+        }
+        log::debug!("Evicting {} expired cache entries", expired_keys.len());
+        let db_write = self.db.begin_write()?;
+        {
+            let mut table = db_write.open_table(REDB_TABLE)?;
+            for key in &expired_keys {
+                table.remove(key.as_str())?;
+            }
+        }
+        db_write.commit()?;
+        self.db.compact()?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -108,10 +201,13 @@ mod tests {
     #[derive(bitcode::Encode, bitcode::Decode)]
     struct Data(String);
 
+    /// A max age long enough that entries set up by these tests never expire by themselves
+    const LONG_MAX_AGE: Duration = Duration::from_secs(3600);
+
     #[test]
     fn set_get() {
         let temp_file = tempfile::NamedTempFile::new().unwrap();
-        let mut cache = Cache::new(temp_file.path()).unwrap();
+        let mut cache = Cache::new(temp_file.path(), LONG_MAX_AGE).unwrap();
         cache
             .set_multi(&[
                 ("key1", &Data("value1".to_owned())),
@@ -126,7 +222,7 @@ mod tests {
     fn set_get_new_cache() {
         let temp_file = tempfile::NamedTempFile::new().unwrap();
         {
-            let mut cache = Cache::new(temp_file.path()).unwrap();
+            let mut cache = Cache::new(temp_file.path(), LONG_MAX_AGE).unwrap();
             cache
                 .set_multi(&[
                     ("key1", &Data("value1".to_owned())),
@@ -134,8 +230,25 @@ mod tests {
                 ])
                 .unwrap();
         }
-        let cache = Cache::new(temp_file.path()).unwrap();
+        let cache = Cache::new(temp_file.path(), LONG_MAX_AGE).unwrap();
         assert_eq!(cache.get::<_, Data>("key1").unwrap().unwrap().0, "value1");
         assert_eq!(cache.get::<_, Data>("key2").unwrap().unwrap().0, "value2");
     }
+
+    #[test]
+    fn get_treats_expired_entry_as_miss() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let mut cache = Cache::new(temp_file.path(), Duration::ZERO).unwrap();
+        cache.set("key1", &Data("value1".to_owned())).unwrap();
+        assert!(cache.get::<_, Data>("key1").unwrap().is_none());
+    }
+
+    #[test]
+    fn evict_removes_expired_entries_and_compacts() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let mut cache = Cache::new(temp_file.path(), LONG_MAX_AGE).unwrap();
+        cache.set("key1", &Data("value1".to_owned())).unwrap();
+        cache.evict(Duration::ZERO).unwrap();
+        assert!(cache.get::<_, Data>("key1").unwrap().is_none());
+    }
 }
@@ -1,26 +1,129 @@
 //! Common HTTP code
 
-use std::{fs, time::Duration};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use anyhow::Context as _;
 use cache::Cache;
+use reqwest::header::HeaderMap;
+
+use crate::{
+    cl::{SearchOptions, SourceName},
+    source::{self, RateLimit},
+};
 
 mod cache;
 
-/// Per source HTTP interface
-pub(crate) struct Http {
+/// How long an HTTP cache entry is kept before it is treated as stale and evicted
+const CACHE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// One HTTP client per configured cover source, built once and shared (via `Arc`) across every
+/// search performed during a run. Building a `SourceHttpClient` opens that source's on-disk cache
+/// file and scans it for eviction (see `cache::Cache::new`), so reconstructing one per search
+/// (eg. per album in a recursive scan) would redo that work every time, and would have concurrent
+/// workers race to open the same cache file
+pub struct SourceClients(HashMap<SourceName, Arc<SourceHttpClient>>);
+
+impl SourceClients {
+    /// Build one HTTP client per source in `search_opts.cover_sources`
+    pub fn build(search_opts: &SearchOptions) -> anyhow::Result<Self> {
+        let mut clients = HashMap::with_capacity(search_opts.cover_sources.len());
+        for source_name in &search_opts.cover_sources {
+            let source = source::build_source(source_name, search_opts)?;
+            let client = SourceHttpClient::new(
+                source_name.as_ref(),
+                source.user_agent(),
+                source.timeout(),
+                source.common_headers(),
+                source.rate_limit(),
+            )
+            .with_context(|| format!("Failed to initialize HTTP client for {source_name}"))?;
+            clients.insert(source_name.clone(), Arc::new(client));
+        }
+        Ok(Self(clients))
+    }
+
+    /// Get the shared HTTP client for `source_name`
+    pub(crate) fn get(&self, source_name: &SourceName) -> anyhow::Result<Arc<SourceHttpClient>> {
+        self.0
+            .get(source_name)
+            .cloned()
+            .with_context(|| format!("No HTTP client built for source {source_name}"))
+    }
+}
+
+/// Per source HTTP client, shareable across concurrently running tasks
+pub(crate) struct SourceHttpClient {
     /// Client
     client: reqwest::Client,
     /// Local cache
-    cache: Cache,
+    cache: Mutex<Cache>,
+    /// Rate limiter, if the source requires one
+    rate_limiter: Option<RateLimiter>,
+}
+
+/// Tracks recent request timestamps to enforce a `RateLimit`
+struct RateLimiter {
+    /// Limit to respect
+    limit: RateLimit,
+    /// Timestamps of requests sent within the current window
+    recent_requests: Mutex<VecDeque<Instant>>,
 }
 
-impl Http {
-    /// Create a new HTTP client
-    pub(crate) fn new(cache_name: &str, ua: &str, timeout: Duration) -> anyhow::Result<Self> {
+impl RateLimiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            recent_requests: Mutex::new(VecDeque::with_capacity(limit.max_count as usize)),
+        }
+    }
+
+    /// Wait until a new request is allowed under the rate limit
+    async fn wait(&self) {
+        loop {
+            let sleep_for = {
+                #[expect(clippy::unwrap_used)]
+                let mut recent_requests = self.recent_requests.lock().unwrap();
+                let now = Instant::now();
+                while recent_requests
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) >= self.limit.time)
+                {
+                    recent_requests.pop_front();
+                }
+                if recent_requests.len() < self.limit.max_count as usize {
+                    recent_requests.push_back(now);
+                    None
+                } else {
+                    #[expect(clippy::unwrap_used)]
+                    Some(self.limit.time - now.duration_since(*recent_requests.front().unwrap()))
+                }
+            };
+            match sleep_for {
+                None => return,
+                Some(duration) => tokio::time::sleep(duration).await,
+            }
+        }
+    }
+}
+
+impl SourceHttpClient {
+    /// Create a new HTTP client for a source
+    pub(crate) fn new(
+        cache_name: &str,
+        ua: &str,
+        timeout: Duration,
+        headers: HeaderMap,
+        rate_limit: Option<RateLimit>,
+    ) -> anyhow::Result<Self> {
         let client = reqwest::Client::builder()
             .user_agent(ua)
             .timeout(timeout)
+            .default_headers(headers)
             .build()
             .context("Failed to create HTTP client")?;
 
@@ -30,36 +133,71 @@ impl Http {
         fs::create_dir_all(cache_dir)
             .with_context(|| format!("Failed to create dir {cache_dir:?}"))?;
         let cache_path = cache_dir.join(format!("http_{cache_name}.db"));
-        let cache = Cache::new(&cache_path)
+        // Opening the cache also evicts any entries already stale, so the database self-maintains
+        // without a separate periodic task
+        let cache = Cache::new(&cache_path, CACHE_MAX_AGE)
             .with_context(|| format!("Failed to open cache at {cache_path:?}"))?;
 
-        Ok(Self { client, cache })
+        Ok(Self {
+            client,
+            cache: Mutex::new(cache),
+            rate_limiter: rate_limit.map(RateLimiter::new),
+        })
     }
 
     /// Send a GET request to URL or get it from cache, parse response as JSON
-    pub(crate) async fn get_json<R>(&mut self, url: reqwest::Url) -> anyhow::Result<R>
+    pub(crate) async fn get_json<R>(&self, url: reqwest::Url) -> anyhow::Result<R>
     where
         R: serde::de::DeserializeOwned + bitcode::Encode + bitcode::DecodeOwned,
     {
         let cache_key = url.as_str().to_owned();
-        if let Some(cache_hit) = self
-            .cache
-            .get::<_, R>(&cache_key)
-            .with_context(|| format!("Cache retrieval failed for key {cache_key:?}"))?
-        {
+        if let Some(cache_hit) = self.cache_get::<R>(&cache_key)? {
             log::trace!("Cache hit for key {cache_key:?}");
-            Ok(cache_hit)
-        } else {
-            let response = self
-                .client
-                .get(url)
-                .send()
-                .await
-                .with_context(|| format!("HTTP error for URL {cache_key:?}"))?;
-            let data = response.bytes().await?;
-            let r: R = serde_json::from_slice(&data)?;
-            self.cache.set(&cache_key, &r)?;
-            Ok(r)
+            return Ok(cache_hit);
         }
+        let data = self.get_bytes(url).await?;
+        let r: R = serde_json::from_slice(&data)?;
+        self.cache_set(&cache_key, &r)?;
+        Ok(r)
+    }
+
+    /// Send a GET request to URL and return the raw response body, bypassing the cache
+    pub(crate) async fn get_bytes(&self, url: reqwest::Url) -> anyhow::Result<bytes::Bytes> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait().await;
+        }
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .with_context(|| format!("HTTP error for URL {url}"))?
+            .error_for_status()
+            .with_context(|| format!("HTTP error for URL {url}"))?;
+        Ok(response.bytes().await?)
+    }
+
+    fn cache_get<V>(&self, key: &str) -> anyhow::Result<Option<V>>
+    where
+        V: bitcode::DecodeOwned,
+    {
+        #[expect(clippy::unwrap_used)]
+        self.cache
+            .lock()
+            .unwrap()
+            .get(key)
+            .with_context(|| format!("Cache retrieval failed for key {key:?}"))
+    }
+
+    fn cache_set<V>(&self, key: &str, value: &V) -> anyhow::Result<()>
+    where
+        V: bitcode::Encode,
+    {
+        #[expect(clippy::unwrap_used)]
+        self.cache
+            .lock()
+            .unwrap()
+            .set(key, value)
+            .with_context(|| format!("Cache write failed for key {key:?}"))
     }
 }
@@ -1,68 +1,149 @@
 //! Internal API exposed for sacad binaries
 
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use anyhow::Context as _;
+use futures::stream::{FuturesUnordered, StreamExt as _};
 
 use crate::{
-    cl::{ImageOutputArgs, SearchArgs},
-    http::Http,
+    cl::{ImageProcessingArgs, SearchOptions, SearchQuery, SourceName},
+    http::SourceHttpClient,
     source::Source,
 };
 
 pub mod cl;
 mod cover;
 mod http;
+mod perceptual_hash;
+pub mod recursive;
 mod source;
+pub mod tags;
+pub mod walk;
 
-/// Search for a cover, sort results, and download the first one that succeeds
+pub use http::SourceClients;
+pub use source::{cache::SearchCache, subsonic::RemoteAlbum};
+
+/// List every album in a configured Subsonic library, to drive a library scan against a remote
+/// server instead of the local filesystem
+pub async fn list_subsonic_albums(search_opts: &SearchOptions) -> anyhow::Result<Vec<RemoteAlbum>> {
+    let source = source::subsonic::Subsonic::new(
+        search_opts
+            .subsonic_url
+            .as_deref()
+            .context("--subsonic-url is required for --subsonic-scan")?,
+        search_opts
+            .subsonic_user
+            .as_deref()
+            .context("--subsonic-user is required for --subsonic-scan")?,
+        search_opts
+            .subsonic_password
+            .as_deref()
+            .context("--subsonic-password is required for --subsonic-scan")?,
+    );
+    let mut http = Arc::new(
+        SourceHttpClient::new(
+            SourceName::Subsonic.as_ref(),
+            source.user_agent(),
+            source.timeout(),
+            source.common_headers(),
+            source.rate_limit(),
+        )
+        .context("Failed to initialize HTTP client")?,
+    );
+    source.list_albums(&mut http).await
+}
+
+/// Outcome of a cover search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStatus {
+    /// A cover was found and downloaded
+    Found,
+    /// No matching cover could be found
+    NotFound,
+}
+
+/// Maximum number of cover sources queried concurrently, to avoid bursting every configured
+/// source's API at once
+const MAX_CONCURRENT_SOURCES: usize = 4;
+
+/// Search for a cover, sort results, and download the first one that succeeds to `output_filepath`.
+/// `cache` deduplicates identical artist/album searches across calls (eg. for multi-disc albums).
+/// `source_clients` must already have a client built for every source in `search_opts.cover_sources`
+/// (see `SourceClients::build`); it is shared across calls so that each source's HTTP client and
+/// on-disk cache are only ever opened once per run, however many albums are searched
 pub async fn search_and_download(
-    search: SearchArgs,
-    output: ImageOutputArgs,
-) -> anyhow::Result<()> {
-    // Search
-    let search = Arc::new(search);
-    let mut sources_searches = Vec::with_capacity(search.cover_sources.len());
-    for source_name in &search.cover_sources {
-        let source: Box<dyn Source> = source_name.into();
-        let mut http = Http::new(source_name.as_ref(), source.user_agent(), source.timeout())
-            .context("Failed to initialize HTTP")?;
-        let search = Arc::clone(&search);
+    output_filepath: &Path,
+    query: Arc<SearchQuery>,
+    search_opts: Arc<SearchOptions>,
+    image_proc: &ImageProcessingArgs,
+    cache: &Arc<SearchCache>,
+    source_clients: &SourceClients,
+) -> anyhow::Result<SearchStatus> {
+    // Search, one task per source running concurrently, results collected as each finishes; a
+    // failing source is logged and skipped. Dedup, sort and download start once all are done
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_SOURCES));
+    let mut sources_searches = FuturesUnordered::new();
+    for source_name in &search_opts.cover_sources {
+        let source = match source::build_source(source_name, &search_opts) {
+            Ok(source) => source,
+            Err(err) => {
+                log::error!("Skipping source {source_name}: {err:#}");
+                continue;
+            }
+        };
+        let mut http = match source_clients.get(source_name) {
+            Ok(http) => http,
+            Err(err) => {
+                log::error!("Skipping source {source_name}: {err:#}");
+                continue;
+            }
+        };
+        let query = Arc::clone(&query);
+        let cache = Arc::clone(cache);
+        let source_name = source_name.clone();
+        let semaphore = Arc::clone(&semaphore);
         sources_searches.push(tokio::spawn(async move {
-            source.search(&search, &mut http).await
+            #[expect(clippy::unwrap_used)] // semaphore is never closed
+            let _permit = semaphore.acquire_owned().await.unwrap();
+            cache
+                .get_or_search(
+                    source_name.as_ref(),
+                    source.as_ref(),
+                    &query.artist,
+                    &query.album,
+                    query.release_mbid.as_deref(),
+                    &mut http,
+                )
+                .await
         }));
     }
-    let mut results: Vec<_> = futures::future::join_all(sources_searches)
-        .await
-        .into_iter()
-        .filter_map(|res| {
-            res.inspect_err(|err| {
-                log::error!("Failed to get source search results: {err:#}");
-            })
-            .ok()
-        })
-        .filter_map(|res| {
-            res.inspect_err(|err| {
-                log::error!("Source failed with error: {err:#}");
-            })
-            .ok()
-        })
-        .collect();
+    let mut results = Vec::new();
+    while let Some(res) = sources_searches.next().await {
+        match res {
+            Ok(Ok(source_results)) => results.extend((*source_results).clone()),
+            Ok(Err(err)) => log::error!("Source failed with error: {err:#}"),
+            Err(err) => log::error!("Failed to get source search results: {err:#}"),
+        }
+    }
+
+    // Drop cross-source duplicates (the same artwork served by more than one source) before
+    // ranking, so a weaker source's copy of a cover already found elsewhere can't shadow it
+    cover::dedup_by_perceptual_hash(&mut results).await;
 
     // Sort
-    cover::sort(&mut results, &search);
+    cover::sort(&mut results, &search_opts, image_proc);
 
     // Download
     for result in results {
-        match result.download(&output) {
-            Ok(()) => return Ok(()),
+        match result.download(output_filepath, image_proc).await {
+            Ok(()) => return Ok(SearchStatus::Found),
             Err(err) => {
                 log::error!("Download of {result} failed: {err:#}");
             }
         }
     }
 
-    log::warn!("No cover to download");
+    log::warn!("No cover to download for {} - {}", query.artist, query.album);
 
-    Ok(())
+    Ok(SearchStatus::NotFound)
 }
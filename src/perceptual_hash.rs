@@ -3,7 +3,7 @@
 use anyhow::Context as _;
 
 /// Image perceptual hash
-#[cfg(any(feature = "ahash", feature = "dhash"))]
+#[cfg(any(feature = "ahash", feature = "dhash", feature = "phash"))]
 pub(crate) struct PerceptualHash(u64);
 #[cfg(feature = "blockhash")]
 pub(crate) struct PerceptualHash(blockhash::Blockhash64);
@@ -84,11 +84,89 @@ impl PerceptualHash {
         let hash = blockhash::blockhash64(&img);
         Ok(Self(hash))
     }
+    #[cfg(feature = "phash")]
+    pub(crate) fn from_image_buffer(buf: &[u8]) -> anyhow::Result<Self> {
+        // DCT-based perceptual hash, robust to gamma/contrast shifts between sources
+        // See https://www.hackerfactor.com/blog/index.php?/archives/432-Looks-Like-It.html
+
+        const PERCEPTUAL_HASH_IMG_SIZE: u32 = 32;
+        const DCT_LOW_FREQ_SIZE: usize = 8;
+
+        // Decode & resize image
+        let img = image::load_from_memory(buf)
+            .context("Failed to decode thumbnail")?
+            .resize_exact(
+                PERCEPTUAL_HASH_IMG_SIZE,
+                PERCEPTUAL_HASH_IMG_SIZE,
+                image::imageops::FilterType::Lanczos3,
+            )
+            .to_luma8();
+
+        // Build the NxN matrix of luma values
+        #[expect(clippy::cast_possible_truncation)]
+        let size = PERCEPTUAL_HASH_IMG_SIZE as usize;
+        let pixels = img.as_raw();
+        #[expect(clippy::indexing_slicing)]
+        let matrix: Vec<Vec<f64>> = (0..size)
+            .map(|row| {
+                (0..size)
+                    .map(|col| f64::from(pixels[row * size + col]))
+                    .collect()
+            })
+            .collect();
+
+        // 2-D DCT-II: rows then columns
+        let rows_dct: Vec<Vec<f64>> = matrix.iter().map(|row| dct_1d(row)).collect();
+        let mut dct = vec![vec![0.0; size]; size];
+        for col in 0..size {
+            #[expect(clippy::indexing_slicing)]
+            let column: Vec<f64> = rows_dct.iter().map(|row| row[col]).collect();
+            let column_dct = dct_1d(&column);
+            #[expect(clippy::indexing_slicing)]
+            for (row, value) in column_dct.into_iter().enumerate() {
+                dct[row][col] = value;
+            }
+        }
+
+        // Keep the low frequency 8x8 block, dropping the [0,0] DC coefficient
+        #[expect(clippy::indexing_slicing)]
+        let low_freq: Vec<f64> = (0..DCT_LOW_FREQ_SIZE)
+            .flat_map(|row| (0..DCT_LOW_FREQ_SIZE).map(move |col| (row, col)))
+            .filter(|&(row, col)| (row, col) != (0, 0))
+            .map(|(row, col)| dct[row][col])
+            .collect();
+
+        // Compute hash from the median of the remaining coefficients
+        let mut sorted = low_freq.clone();
+        #[expect(clippy::unwrap_used)]
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        #[expect(clippy::indexing_slicing)]
+        let median = sorted[sorted.len() / 2];
+
+        let hash = low_freq
+            .iter()
+            .enumerate()
+            .fold(0_u64, |mut hash, (i, coeff)| {
+                if *coeff > median {
+                    hash |= 1 << i;
+                }
+                hash
+            });
+
+        Ok(Self(hash))
+    }
 
     /// Return true if both hashes seem to refer to a similar image
-    #[cfg(any(feature = "ahash", feature = "dhash"))]
+    #[cfg(any(feature = "ahash", feature = "dhash", feature = "phash"))]
     pub(crate) fn is_similar(&self, other: &Self) -> bool {
-        const MAX_HAMMING_DELTA: u32 = if cfg!(feature = "ahash") { 5 } else { 8 };
+        const MAX_HAMMING_DELTA: u32 = if cfg!(feature = "ahash") {
+            5
+        } else if cfg!(feature = "dhash") {
+            8
+        } else {
+            // phash: tuned empirically, DCT coefficients are more resilient to noise than ahash/dhash
+            8
+        };
         (self.0 ^ other.0).count_ones() < MAX_HAMMING_DELTA
     }
     #[cfg(feature = "blockhash")]
@@ -98,7 +176,7 @@ impl PerceptualHash {
     }
 
     #[cfg(test)]
-    #[cfg(any(feature = "ahash", feature = "dhash"))]
+    #[cfg(any(feature = "ahash", feature = "dhash", feature = "phash"))]
     pub(crate) fn test_similar() -> Self {
         Self(0)
     }
@@ -110,7 +188,7 @@ impl PerceptualHash {
     }
 
     #[cfg(test)]
-    #[cfg(any(feature = "ahash", feature = "dhash"))]
+    #[cfg(any(feature = "ahash", feature = "dhash", feature = "phash"))]
     pub(crate) fn test_dissimilar() -> Self {
         Self(u64::MAX)
     }
@@ -121,3 +199,23 @@ impl PerceptualHash {
         Self(blockhash::Blockhash64::from([0xFF; 8]))
     }
 }
+
+/// 1-D type-II DCT, applied to rows then columns to get a 2-D DCT
+#[cfg(feature = "phash")]
+fn dct_1d(input: &[f64]) -> Vec<f64> {
+    #[expect(clippy::cast_precision_loss)]
+    let n = input.len() as f64;
+    (0..input.len())
+        .map(|u| {
+            #[expect(clippy::cast_precision_loss)]
+            let sum: f64 = input
+                .iter()
+                .enumerate()
+                .map(|(k, x)| {
+                    x * (std::f64::consts::PI / n * (k as f64 + 0.5) * u as f64).cos()
+                })
+                .sum();
+            sum
+        })
+        .collect()
+}
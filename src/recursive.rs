@@ -0,0 +1,500 @@
+//! Recursively search and download album covers for a music library. Shared between the
+//! `sacad-recursive` binary and the `sacad scan` subcommand
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, LazyLock, atomic::Ordering},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use async_channel::Receiver;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::{
+    SearchCache, SourceClients,
+    cl::{self, CoverOutput, ImageProcessingArgs, SacadRecursiveArgs, SearchOptions, SearchQuery},
+    list_subsonic_albums, search_and_download, tags,
+    walk::{AudioFileIterator, Stats},
+};
+
+/// Unit of work for worker tasks
+#[derive(Debug)]
+struct Work {
+    /// Query to search for
+    query: SearchQuery,
+    /// Where to output the cover
+    output: WorkOutput,
+}
+
+/// Where to output a cover
+#[derive(Debug)]
+enum WorkOutput {
+    /// Embed into tags for given files
+    Embed(Vec<PathBuf>),
+    /// Write to file
+    File(PathBuf),
+}
+
+/// Wrapper from the same type in `cl` module to add path conversion
+struct CoverOutputPattern<S>(cl::CoverOutputPattern<S>);
+
+impl<S: Clone> From<&cl::CoverOutputPattern<S>> for CoverOutputPattern<S> {
+    fn from(value: &cl::CoverOutputPattern<S>) -> Self {
+        Self(value.clone())
+    }
+}
+
+impl<S: AsRef<str>> CoverOutputPattern<S> {
+    #[cfg(test)]
+    fn new(s: S) -> Self {
+        Self(cl::CoverOutputPattern(s))
+    }
+
+    /// Replace `{artist}` and `{album}` placeholders in pattern
+    fn to_path_buf(&self, artist: &str, album: &str) -> PathBuf {
+        let safe_artist = Self::sanitize_for_path(artist);
+        let safe_album = Self::sanitize_for_path(album);
+        let path = self
+            .0
+            .0
+            .as_ref()
+            .replace("{artist}", &safe_artist)
+            .replace("{album}", &safe_album);
+        PathBuf::from(path)
+    }
+
+    fn sanitize_for_path(s: &str) -> String {
+        static VALID_ASCII_PUNCTUATION: LazyLock<HashSet<char>> =
+            LazyLock::new(|| "-_.()!#$%&'@^{}~".chars().collect());
+        s.chars()
+            .filter_map(|c| match c {
+                '/' | '\\' => Some('-'),
+                '|' | '*' => Some('x'),
+                c if c.is_ascii_alphanumeric()
+                    || VALID_ASCII_PUNCTUATION.contains(&c)
+                    || (c == ' ') =>
+                {
+                    Some(c)
+                }
+                _ => None,
+            })
+            .collect::<String>()
+            .trim_matches([' ', '.'])
+            .chars()
+            .collect()
+    }
+}
+
+/// Worker entry point
+async fn worker(
+    work_rx: Receiver<Work>,
+    search_opts: Arc<SearchOptions>,
+    image_proc: Arc<ImageProcessingArgs>,
+    cache: Arc<SearchCache>,
+    source_clients: Arc<SourceClients>,
+    stats: Arc<Stats>,
+    progress_bar: ProgressBar,
+) -> anyhow::Result<()> {
+    while let Ok(work) = work_rx.recv().await {
+        if let Err(err) = handle_work(
+            work,
+            &search_opts,
+            &image_proc,
+            &cache,
+            &source_clients,
+            &stats,
+            &progress_bar,
+        )
+        .await
+        {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+            log::warn!("{err}");
+        }
+    }
+    Ok(())
+}
+
+/// Read tags for one album's audio files and, if it is missing a cover, send work downstream
+fn read_tags_and_send_work(
+    audio_files: Vec<PathBuf>,
+    cover_output: &CoverOutput,
+    ignore_existing: bool,
+    work_tx: &async_channel::Sender<Work>,
+    stats: &Arc<Stats>,
+) {
+    let Some(tags) = tags::read_metadata(&audio_files, matches!(cover_output, CoverOutput::Embed))
+    else {
+        log::warn!("Unable to extract metadata from files {audio_files:?}");
+        stats.errors.fetch_add(1, Ordering::Relaxed);
+        return;
+    };
+
+    let output = match cover_output {
+        CoverOutput::Embed => WorkOutput::Embed(audio_files),
+        CoverOutput::Pattern(pattern) => {
+            let pattern: CoverOutputPattern<_> = pattern.into();
+            WorkOutput::File(pattern.to_path_buf(&tags.artist, &tags.album))
+        }
+    };
+
+    let has_cover = match &output {
+        #[expect(clippy::unwrap_used)]
+        WorkOutput::Embed(_) => tags.has_embedded_cover.unwrap(),
+        WorkOutput::File(path) => path.exists(),
+    };
+    if has_cover {
+        stats.already_has_cover.fetch_add(1, Ordering::Relaxed);
+        if !ignore_existing {
+            return;
+        }
+    }
+    // Counts every album whose cover is actually searched for, including a re-fetch forced by
+    // --ignore-existing: the progress bar length and the "done"/"not_found"/"errors" summary
+    // counters are both driven by this count, so it must match the number of searches started
+    stats.missing_covers.fetch_add(1, Ordering::Relaxed);
+
+    let query = SearchQuery {
+        artist: tags.artist,
+        album: tags.album,
+        release_mbid: tags.release_mbid,
+    };
+    let _ = work_tx.send_blocking(Work { query, output });
+}
+
+/// List every album of a remote Subsonic library and send a download work item for each one.
+/// There are no local audio files to embed into, so `output_pattern` must have been selected
+/// (checked by the caller). Albums whose output file already exists are skipped unless
+/// `ignore_existing` is set
+async fn send_remote_work(
+    search_opts: &SearchOptions,
+    output_pattern: &cl::CoverOutputPattern<String>,
+    ignore_existing: bool,
+    work_tx: &async_channel::Sender<Work>,
+    stats: &Arc<Stats>,
+) -> anyhow::Result<()> {
+    let pattern: CoverOutputPattern<_> = output_pattern.into();
+    for album in list_subsonic_albums(search_opts).await? {
+        stats.audio_dirs.fetch_add(1, Ordering::Relaxed);
+        let output_path = pattern.to_path_buf(&album.artist, &album.album);
+        let has_cover = output_path.exists();
+        if has_cover {
+            stats.already_has_cover.fetch_add(1, Ordering::Relaxed);
+            if !ignore_existing {
+                continue;
+            }
+        }
+        stats.missing_covers.fetch_add(1, Ordering::Relaxed);
+        let query = SearchQuery {
+            artist: album.artist,
+            album: album.album,
+            release_mbid: None,
+        };
+        if work_tx
+            .send(Work {
+                query,
+                output: WorkOutput::File(output_path),
+            })
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Update the progress bar message from current stats
+fn update_progress_bar(stats: &Stats, progress_bar: &ProgressBar) {
+    let done = stats.done.load(Ordering::Relaxed);
+    let no_result = stats.no_result_found.load(Ordering::Relaxed);
+    let errors = stats.errors.load(Ordering::Relaxed);
+    let missing = stats.missing_covers.load(Ordering::Relaxed);
+    let audio_files = stats.audio_files.load(Ordering::Relaxed);
+    let audio_dirs = stats.audio_dirs.load(Ordering::Relaxed);
+
+    progress_bar.set_length(missing.try_into().unwrap_or(u64::MAX));
+    progress_bar.set_position((done + no_result + errors).try_into().unwrap_or(u64::MAX));
+    progress_bar.set_message(format!(
+        "dirs:{audio_dirs} files:{audio_files} missing:{missing} done:{done} not_found:{no_result} errs:{errors}"
+    ));
+}
+
+/// Worker function to handle a single work item
+async fn handle_work(
+    work: Work,
+    search_opts: &Arc<SearchOptions>,
+    image_proc: &Arc<ImageProcessingArgs>,
+    cache: &Arc<SearchCache>,
+    source_clients: &Arc<SourceClients>,
+    stats: &Arc<Stats>,
+    progress_bar: &ProgressBar,
+) -> anyhow::Result<()> {
+    let (output, _tmp_file) = match &work.output {
+        WorkOutput::Embed(_) => {
+            let tmp_file = tempfile::NamedTempFile::new()?;
+            (tmp_file.path().to_owned(), Some(tmp_file))
+        }
+        WorkOutput::File(filepath) => (filepath.to_owned(), None),
+    };
+    match search_and_download(
+        &output,
+        Arc::new(work.query),
+        Arc::clone(search_opts),
+        image_proc,
+        cache,
+        source_clients,
+    )
+    .await?
+    {
+        crate::SearchStatus::Found => {
+            if let WorkOutput::Embed(audio_files) = work.output {
+                tags::embed_cover(&output, None, None, audio_files)?;
+            }
+            stats.done.fetch_add(1, Ordering::Relaxed);
+        }
+        crate::SearchStatus::NotFound => {
+            stats.no_result_found.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    update_progress_bar(stats, progress_bar);
+    Ok(())
+}
+
+/// Recursively scan a music library (or a remote Subsonic library) and fetch covers for every
+/// album missing one, printing a summary once the scan completes
+pub async fn run(cl_args: SacadRecursiveArgs) -> anyhow::Result<()> {
+    // Create progress bar
+    let stats = Arc::default();
+    let progress_bar = ProgressBar::new(0);
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner} [{elapsed_precise}/{duration_precise}] [{bar}] {pos}/{len} {percent}% {wide_msg}")?,
+    );
+    progress_bar.enable_steady_tick(Duration::from_millis(300));
+    update_progress_bar(&stats, &progress_bar);
+
+    // Stage 3: download workers, limited by source rate limits rather than local resources
+    let search_opts = Arc::new(cl_args.search_opts);
+    let image_proc = Arc::new(cl_args.image_proc);
+    let cache = Arc::new(SearchCache::new(Duration::from_secs(
+        search_opts.cache_ttl_secs,
+    )));
+    // Built once and shared across every worker and every album searched, rather than
+    // reconstructed per search: each client owns an on-disk cache that takes an exclusive file
+    // lock, so concurrent workers reopening it per album would fight over that lock
+    let source_clients =
+        Arc::new(SourceClients::build(&search_opts).context("Failed to initialize sources")?);
+    let (work_tx, work_rx) = async_channel::bounded::<Work>(1024);
+    let mut workers = Vec::with_capacity(cl_args.workers);
+    for _ in 0..cl_args.workers {
+        let worker_work_rx = work_rx.clone();
+        let worker_search_opts = Arc::clone(&search_opts);
+        let worker_image_proc = Arc::clone(&image_proc);
+        let worker_cache = Arc::clone(&cache);
+        let worker_source_clients = Arc::clone(&source_clients);
+        let worker_stats = Arc::clone(&stats);
+        let worker_progress_bar = progress_bar.clone();
+        let worker = tokio::spawn(async {
+            if let Err(err) = worker(
+                worker_work_rx,
+                worker_search_opts,
+                worker_image_proc,
+                worker_cache,
+                worker_source_clients,
+                worker_stats,
+                worker_progress_bar,
+            )
+            .await
+            {
+                log::error!("Worker errored: {err}");
+            }
+        });
+        workers.push(worker);
+    }
+
+    let cover_output = cl_args.output();
+
+    if cl_args.subsonic_scan {
+        // Stage 1+2: list the remote library directly into download work items, there is no
+        // local filesystem to traverse or tags to read
+        let CoverOutput::Pattern(output_pattern) = &cover_output else {
+            anyhow::bail!(
+                "--subsonic-scan cannot be combined with --embed: there are no local files to embed into"
+            );
+        };
+        let scanner_search_opts = Arc::clone(&search_opts);
+        let scanner_output_pattern = output_pattern.clone();
+        let scanner_ignore_existing = cl_args.ignore_existing;
+        let scanner_stats = Arc::clone(&stats);
+        let scanner = tokio::spawn(async move {
+            if let Err(err) = send_remote_work(
+                &scanner_search_opts,
+                &scanner_output_pattern,
+                scanner_ignore_existing,
+                &work_tx,
+                &scanner_stats,
+            )
+            .await
+            {
+                log::error!("Remote library scan failed: {err:#}");
+            }
+            update_progress_bar(&scanner_stats, &progress_bar);
+        });
+        scanner.await.context("Remote scan task panicked")?;
+    } else {
+        let lib_root_dir = cl_args
+            .lib_root_dir
+            .context("LIB_ROOT_DIR is required unless --subsonic-scan is set")?;
+
+        // Stage 1: traverser, walks the library and hands off one directory at a time
+        let (dirs_tx, dirs_rx) = async_channel::bounded::<Vec<PathBuf>>(256);
+        let traverser_stats = Arc::clone(&stats);
+        let traverser = tokio::task::spawn_blocking(move || {
+            for audio_files in AudioFileIterator::new(&lib_root_dir, traverser_stats) {
+                if dirs_tx.send_blocking(audio_files).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Stage 2: reader workers, read tags off disk and build download work items
+        let reader_threads = cl_args.reader_threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(std::num::NonZero::get)
+                .unwrap_or(1)
+        });
+        let mut readers = Vec::with_capacity(reader_threads);
+        for _ in 0..reader_threads {
+            let reader_dirs_rx = dirs_rx.clone();
+            let reader_work_tx = work_tx.clone();
+            let reader_cover_output = cover_output.clone();
+            let reader_ignore_existing = cl_args.ignore_existing;
+            let reader_stats = Arc::clone(&stats);
+            let reader_progress_bar = progress_bar.clone();
+            let reader = tokio::task::spawn_blocking(move || {
+                while let Ok(audio_files) = reader_dirs_rx.recv_blocking() {
+                    read_tags_and_send_work(
+                        audio_files,
+                        &reader_cover_output,
+                        reader_ignore_existing,
+                        &reader_work_tx,
+                        &reader_stats,
+                    );
+                    update_progress_bar(&reader_stats, &reader_progress_bar);
+                }
+            });
+            readers.push(reader);
+        }
+        drop(work_tx);
+        drop(dirs_rx);
+
+        traverser.await.context("Traverser thread panicked")?;
+        for reader in readers {
+            reader.await.context("Reader thread panicked")?;
+        }
+    }
+
+    for worker in workers {
+        let _ = worker.await;
+    }
+
+    progress_bar.finish();
+    print_summary(&stats);
+
+    Ok(())
+}
+
+/// Print a final summary of the scan once every album has been processed
+fn print_summary(stats: &Stats) {
+    let audio_dirs = stats.audio_dirs.load(Ordering::Relaxed);
+    let already_has_cover = stats.already_has_cover.load(Ordering::Relaxed);
+    let done = stats.done.load(Ordering::Relaxed);
+    let no_result_found = stats.no_result_found.load(Ordering::Relaxed);
+    let errors = stats.errors.load(Ordering::Relaxed);
+    println!(
+        "Scanned {audio_dirs} album(s): {already_has_cover} already had a cover, {done} newly fetched, {no_result_found} not found, {errors} error(s)"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_pattern_basic_replacement() {
+        let pattern = CoverOutputPattern::new("covers/{artist}/{album}.jpg");
+        let result = pattern.to_path_buf("The Beatles", "Abbey Road");
+        assert_eq!(result, PathBuf::from("covers/The Beatles/Abbey Road.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_single_placeholder() {
+        let pattern = CoverOutputPattern::new("{album}_cover.jpg");
+        let result = pattern.to_path_buf("Artist Name", "Album Name");
+        assert_eq!(result, PathBuf::from("Album Name_cover.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_multiple_occurrences() {
+        let pattern = CoverOutputPattern::new("{artist}_{artist}_{album}.jpg");
+        let result = pattern.to_path_buf("Pink Floyd", "Dark Side");
+        assert_eq!(result, PathBuf::from("Pink Floyd_Pink Floyd_Dark Side.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_no_placeholders() {
+        let pattern = CoverOutputPattern::new("cover.jpg");
+        let result = pattern.to_path_buf("Artist", "Album");
+        assert_eq!(result, PathBuf::from("cover.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_with_special_chars() {
+        let pattern = CoverOutputPattern::new("{artist} - {album}/cover.jpg");
+        let result = pattern.to_path_buf("Metallica", "Master of Puppets");
+        assert_eq!(
+            result,
+            PathBuf::from("Metallica - Master of Puppets/cover.jpg")
+        );
+    }
+
+    #[test]
+    fn output_pattern_sanitizes_forward_slashes() {
+        let pattern = CoverOutputPattern::new("covers/{artist}/{album}.jpg");
+        let result = pattern.to_path_buf("AC/DC", "Back/in Black");
+        // / becomes -
+        assert_eq!(result, PathBuf::from("covers/AC-DC/Back-in Black.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_sanitizes_backslashes() {
+        let pattern = CoverOutputPattern::new("{artist}_{album}.jpg");
+        let result = pattern.to_path_buf("Foo\\Bar", "Album\\Name");
+        // \ becomes -
+        assert_eq!(result, PathBuf::from("Foo-Bar_Album-Name.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_sanitizes_pipes_and_asterisks() {
+        let pattern = CoverOutputPattern::new("{artist}_{album}.jpg");
+        let result = pattern.to_path_buf("Artist|Name", "Album*Name");
+        // | and * become x
+        assert_eq!(result, PathBuf::from("ArtistxName_AlbumxName.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_removes_trailing_dots() {
+        let pattern = CoverOutputPattern::new("{artist}_{album}.jpg");
+        let result = pattern.to_path_buf("Artist.", "Album...");
+        assert_eq!(result, PathBuf::from("Artist_Album.jpg"));
+    }
+
+    #[test]
+    fn output_pattern_trims_whitespace() {
+        let pattern = CoverOutputPattern::new("{artist}_{album}.jpg");
+        let result = pattern.to_path_buf("  Artist  ", "  Album  ");
+        assert_eq!(result, PathBuf::from("Artist_Album.jpg"));
+    }
+}
@@ -0,0 +1,88 @@
+//! In-memory cache of per-source search results, to avoid hammering APIs with repeated
+//! identical queries (eg. one per track of a multi-disc album during a recursive scan)
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::{cover::Cover, http::SourceHttpClient, source::Source};
+
+/// Key identifying a cached search
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    /// Source the search was made against
+    source: String,
+    /// Normalized artist name
+    artist: String,
+    /// Normalized album name
+    album: String,
+    /// `MusicBrainz` release MBID, if one was used for this search
+    release_mbid: Option<String>,
+}
+
+/// A cached entry, populated at most once, holding the results and their insertion time
+type Entry = Arc<OnceCell<(Instant, Arc<Vec<Cover>>)>>;
+
+/// Cache of source search results, shared across all searches performed during a run
+pub struct SearchCache {
+    /// How long an entry remains fresh before it must be refreshed
+    ttl: Duration,
+    /// Cached entries, keyed by source/artist/album
+    entries: Mutex<HashMap<Key, Entry>>,
+}
+
+impl SearchCache {
+    /// Build a new cache whose entries expire after `ttl`
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get cached search results for `(source, artist, album)`, or run `source.search_with_mbid`
+    /// and cache the result. Concurrent calls for the same key share a single underlying search
+    pub(crate) async fn get_or_search(
+        &self,
+        source_name: &str,
+        source: &dyn Source,
+        artist: &str,
+        album: &str,
+        release_mbid: Option<&str>,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Arc<Vec<Cover>>> {
+        let key = Key {
+            source: source_name.to_owned(),
+            artist: super::normalize(artist),
+            album: super::normalize(album),
+            release_mbid: release_mbid.map(str::to_owned),
+        };
+        let cell = self.cell_for(key).await;
+        let (_, results) = cell
+            .get_or_try_init(|| async {
+                let results = source
+                    .search_with_mbid(artist, album, release_mbid, http)
+                    .await?;
+                Ok::<_, anyhow::Error>((Instant::now(), Arc::new(results)))
+            })
+            .await?;
+        Ok(Arc::clone(results))
+    }
+
+    /// Get the cell for `key`, discarding and replacing it first if it is stale
+    async fn cell_for(&self, key: Key) -> Entry {
+        let mut entries = self.entries.lock().await;
+        let stale = entries
+            .get(&key)
+            .is_some_and(|cell| cell.get().is_some_and(|(inserted_at, _)| inserted_at.elapsed() >= self.ttl));
+        if stale {
+            entries.remove(&key);
+        }
+        Arc::clone(entries.entry(key).or_insert_with(|| Arc::new(OnceCell::new())))
+    }
+}
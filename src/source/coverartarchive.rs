@@ -33,15 +33,14 @@ struct MusicBrainzRelease {
 }
 
 impl MusicBrainzRelease {
-    /// Check if the release is a fuzzy match (artist/album don't match exactly)
-    fn is_fuzzy_match(&self, nartist: &str, nalbum: &str) -> bool {
-        let release_album = normalize(&self.title);
-        (release_album != nalbum)
-            || !self
-                .artist_credit
-                .iter()
-                .map(|c| normalize(&c.name))
-                .any(|ac| ac == nartist)
+    /// Score how well this release matches the searched artist/album, using the release's best
+    /// matching artist credit
+    fn match_score(&self, nartist: &str, nalbum: &str) -> u8 {
+        self.artist_credit
+            .iter()
+            .map(|c| source::match_score(nartist, Some(&c.name), nalbum, Some(&self.title)))
+            .max()
+            .unwrap_or(0)
     }
 }
 
@@ -84,11 +83,44 @@ const THUMBNAIL_SIZES: &[(u32, ThumbnailGetter)] = &[
 
 /// Default relevance for Cover Art Archive covers
 const COVERARTARCHIVE_RELEVANCE: source::Relevance = source::Relevance {
-    fuzzy: false,
+    score: 0,
     only_front_covers: true,
     unrelated_risk: false,
 };
 
+/// [`Relevance::score`] used when a release was looked up directly by MBID, which is always exact
+const MBID_LOOKUP_SCORE: u8 = 100;
+
+/// Rows to request per `MusicBrainz` search page
+const PAGE_LIMIT: u32 = 25;
+
+/// Maximum number of releases to accumulate across pages
+const MAX_RELEASES: u32 = 50;
+
+/// Offset-based paging parameters for a `MusicBrainz` search query
+#[derive(Debug, Clone, Copy)]
+struct PageSettings {
+    /// Max rows to request for this page
+    limit: u32,
+    /// Row offset of this page
+    offset: u32,
+}
+
+impl PageSettings {
+    /// First page, with a given page size
+    fn first(limit: u32) -> Self {
+        Self { limit, offset: 0 }
+    }
+
+    /// Settings for the page following this one
+    fn next(self) -> Self {
+        Self {
+            limit: self.limit,
+            offset: self.offset + self.limit,
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl Source for CoverArtArchive {
     async fn search(
@@ -104,8 +136,8 @@ impl Source for CoverArtArchive {
 
         let mut results = Vec::new();
         for (rank, release) in releases.into_iter().enumerate() {
-            let is_fuzzy = release.is_fuzzy_match(&nartist, &nalbum);
-            if let Ok(covers) = self.release_covers(&release.id, rank, is_fuzzy, http).await {
+            let score = release.match_score(&nartist, &nalbum);
+            if let Ok(covers) = self.release_covers(&release.id, rank, score, http).await {
                 results.extend(covers);
             }
         }
@@ -113,6 +145,21 @@ impl Source for CoverArtArchive {
         Ok(results)
     }
 
+    async fn search_with_mbid(
+        &self,
+        artist: &str,
+        album: &str,
+        release_mbid: Option<&str>,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<Cover>> {
+        // A known release MBID lets us skip the MusicBrainz text search entirely and go
+        // straight to Cover Art Archive, saving a rate-limited round-trip
+        if let Some(mbid) = release_mbid {
+            return self.release_covers(mbid, 0, MBID_LOOKUP_SCORE, http).await;
+        }
+        self.search(artist, album, http).await
+    }
+
     fn rate_limit(&self) -> Option<RateLimit> {
         // https://musicbrainz.org/doc/MusicBrainz_API/Rate_Limiting
         Some(RateLimit {
@@ -132,16 +179,37 @@ impl CoverArtArchive {
     ) -> anyhow::Result<Vec<MusicBrainzRelease>> {
         // https://musicbrainz.org/doc/MusicBrainz_API/Search#Release
         let query = format!("artist:\"{artist}\" AND release:\"{album}\"");
-        // Note: set a low result limit because following requests are slow due to rate limit
-        // Note: pagination is also available
-        let url_params = [("query", query.as_str()), ("limit", "8"), ("fmt", "json")];
 
-        #[expect(clippy::unwrap_used)]
-        let search_url =
-            Url::parse_with_params("https://musicbrainz.org/ws/2/release", url_params).unwrap();
+        // Note: following requests are slow due to rate limiting, so stop as soon as we have
+        // enough releases or the server runs out of results
+        let mut releases = Vec::new();
+        let mut page = PageSettings::first(PAGE_LIMIT);
+        loop {
+            let limit = page.limit.to_string();
+            let offset = page.offset.to_string();
+            let url_params = [
+                ("query", query.as_str()),
+                ("limit", limit.as_str()),
+                ("offset", offset.as_str()),
+                ("fmt", "json"),
+            ];
+
+            #[expect(clippy::unwrap_used)]
+            let search_url =
+                Url::parse_with_params("https://musicbrainz.org/ws/2/release", url_params).unwrap();
+
+            let resp: MusicBrainzReleaseSearchResponse = http.get_json(search_url).await?;
+            let page_len = resp.releases.len();
+            releases.extend(resp.releases);
+
+            if (page_len < page.limit as usize) || (releases.len() >= MAX_RELEASES as usize) {
+                break;
+            }
+            page = page.next();
+        }
+        releases.truncate(MAX_RELEASES as usize);
 
-        let resp: MusicBrainzReleaseSearchResponse = http.get_json(search_url).await?;
-        Ok(resp.releases)
+        Ok(releases)
     }
 
     /// Fetch cover art from Cover Art Archive for a given release MBID
@@ -149,7 +217,7 @@ impl CoverArtArchive {
         &self,
         mbid: &str,
         rank: usize,
-        is_fuzzy: bool,
+        score: u8,
         http: &mut Arc<SourceHttpClient>,
     ) -> anyhow::Result<Vec<Cover>> {
         #[expect(clippy::unwrap_used)]
@@ -176,7 +244,7 @@ impl CoverArtArchive {
             };
 
             let relevance = source::Relevance {
-                fuzzy: is_fuzzy,
+                score,
                 ..COVERARTARCHIVE_RELEVANCE
             };
             for (size, url) in thumbnails {
@@ -53,7 +53,7 @@ const COVER_SIZES: &[(&str, u32)] = &[
 
 /// Default relevance for Deezer covers
 const DEEZER_RELEVANCE: source::Relevance = source::Relevance {
-    fuzzy: false,
+    score: 0,
     only_front_covers: true,
     unrelated_risk: false,
 };
@@ -120,8 +120,12 @@ impl Source for Deezer {
                     source_name: SourceName::Deezer,
                     source_http: Arc::clone(http),
                     relevance: source::Relevance {
-                        fuzzy: (normalize(&result.artist.name) != nartist)
-                            || (normalize(&result.album.title) != nalbum),
+                        score: source::match_score(
+                            &nartist,
+                            Some(&result.artist.name),
+                            &nalbum,
+                            Some(&result.album.title),
+                        ),
                         ..DEEZER_RELEVANCE
                     },
                     rank,
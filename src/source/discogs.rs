@@ -29,7 +29,7 @@ const API_SECRET: &str = "NCyWcKHWLAvAreyjDdvVogBzVnzPEEDf";
 
 /// Default relevance for Discogs covers
 const DISCOGS_RELEVANCE: source::Relevance = source::Relevance {
-    fuzzy: false,
+    score: 0,
     only_front_covers: false,
     unrelated_risk: false,
 };
@@ -41,11 +41,20 @@ struct Response {
 
 #[derive(Debug, serde::Deserialize)]
 struct ResponseResult {
+    /// Formatted as `"Artist - Album"`
+    title: String,
     thumb: String,
     cover_image: String,
     formats: Vec<ResponseResultFormat>,
 }
 
+impl ResponseResult {
+    /// Split `title` into its artist and album parts, if it follows the usual `"Artist - Album"` form
+    fn artist_and_album(&self) -> Option<(&str, &str)> {
+        self.title.split_once(" - ")
+    }
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct ResponseResultFormat {
     name: String,
@@ -119,6 +128,14 @@ impl Source for Discogs {
                 .parse()
                 .with_context(|| format!("Unable to parse thumbnail URL {:?}", result.thumb))?;
 
+            let (result_artist, result_album) = result
+                .artist_and_album()
+                .map_or((None, None), |(a, t)| (Some(a), Some(t)));
+            let relevance = source::Relevance {
+                score: source::match_score(&nartist, result_artist, &nalbum, result_album),
+                ..DISCOGS_RELEVANCE
+            };
+
             let cover = Cover {
                 url,
                 thumbnail_url,
@@ -126,7 +143,7 @@ impl Source for Discogs {
                 format: Metadata::known(Format::Jpeg),
                 source_name: SourceName::Discogs,
                 source_http: Arc::clone(http),
-                relevance: DISCOGS_RELEVANCE,
+                relevance,
                 rank,
             };
             results.push(cover);
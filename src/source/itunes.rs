@@ -1,39 +1,128 @@
 //! Itunes cover source
 
+use std::sync::Arc;
+
+use anyhow::Context as _;
 use reqwest::Url;
 
-use crate::{cl::SearchArgs, cover::Cover, http::Http, source::Source};
+use crate::{
+    cl::SourceName,
+    cover::{Cover, Format, Metadata},
+    http::SourceHttpClient,
+    source::{self, Source, normalize},
+};
 
 /// Itunes cover source
 pub(crate) struct Itunes;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, bitcode::Encode, bitcode::Decode)]
-#[expect(clippy::missing_docs_in_private_items)]
+/// Large artwork size to request by rewriting the size token in `artworkUrl100`
+const LARGE_SIZE_PX: u32 = 2000;
+
+/// Default relevance for Itunes covers. Always an exact match since non-matching results are
+/// filtered out in `search`
+const ITUNES_RELEVANCE: source::Relevance = source::Relevance {
+    score: 100,
+    only_front_covers: true,
+    unrelated_risk: false,
+};
+
+#[derive(Debug, serde::Deserialize)]
 struct Response {
     results: Vec<ResponseResult>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, bitcode::Encode, bitcode::Decode)]
+#[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
-#[expect(clippy::missing_docs_in_private_items)]
 struct ResponseResult {
     collection_name: String,
     artist_name: String,
-    artwork_url_60: String,
     artwork_url_100: String,
 }
 
+/// Rewrite an Itunes artwork CDN URL's trailing `WxHbb` size token to request a larger image
+fn upscale_artwork_url(url: &str, size_px: u32) -> Option<String> {
+    let (base, _) = url.rsplit_once('/')?;
+    let ext = url.rsplit_once('.').map_or("jpg", |(_, ext)| ext);
+    Some(format!("{base}/{size_px}x{size_px}bb.{ext}"))
+}
+
 #[async_trait::async_trait]
 impl Source for Itunes {
-    async fn search(&self, query: &SearchArgs, http: &mut Http) -> anyhow::Result<Cover> {
-        let url_term = format!("{} {}", query.artist, query.album);
+    async fn search(
+        &self,
+        artist: &str,
+        album: &str,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<Cover>> {
+        let nartist = normalize(artist);
+        let nalbum = normalize(album);
+
+        let url_term = format!("{artist} {album}");
         let url_params = [("media", "music"), ("entity", "album"), ("term", &url_term)];
         #[expect(clippy::unwrap_used)] // base URL is absolute
-        let url = Url::parse_with_params("https://itunes.apple.com/search", url_params).unwrap();
-        let resp: Response = http.get_json(url).await?;
+        let search_url = Url::parse_with_params("https://itunes.apple.com/search", url_params).unwrap();
+        let resp: Response = http.get_json(search_url).await?;
+
+        let mut results = Vec::new();
         for (rank, result) in resp.results.into_iter().enumerate() {
-            todo!();
+            if (normalize(&result.artist_name) != nartist) || (normalize(&result.collection_name) != nalbum) {
+                continue;
+            }
+
+            let thumbnail_url: Url = result
+                .artwork_url_100
+                .parse()
+                .with_context(|| format!("Failed to parse thumbnail URL {:?}", result.artwork_url_100))?;
+
+            let Some(large_url_str) = upscale_artwork_url(&result.artwork_url_100, LARGE_SIZE_PX) else {
+                continue;
+            };
+            let url: Url = large_url_str
+                .parse()
+                .with_context(|| format!("Failed to parse cover URL {large_url_str:?}"))?;
+
+            let cover = Cover {
+                url,
+                thumbnail_url,
+                size_px: Metadata::uncertain((LARGE_SIZE_PX, LARGE_SIZE_PX)),
+                format: Metadata::uncertain(Format::Jpeg),
+                source_name: SourceName::Itunes,
+                source_http: Arc::clone(http),
+                relevance: ITUNES_RELEVANCE,
+                rank,
+            };
+            results.push(cover);
         }
-        todo!();
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::tests::{source_has_results, source_no_results};
+
+    #[test]
+    fn upscale_artwork_url_rewrites_size_token() {
+        let url = "https://is1-ssl.mzstatic.com/image/thumb/abc/100x100bb.jpg";
+        assert_eq!(
+            upscale_artwork_url(url, 600),
+            Some("https://is1-ssl.mzstatic.com/image/thumb/abc/600x600bb.jpg".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn has_results() {
+        let _ = simple_logger::init_with_env();
+        let source = Itunes;
+        source_has_results(source, SourceName::Itunes).await;
+    }
+
+    #[tokio::test]
+    async fn has_no_results() {
+        let _ = simple_logger::init_with_env();
+        let source = Itunes;
+        source_no_results(source, SourceName::Itunes).await;
     }
 }
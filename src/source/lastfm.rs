@@ -1,6 +1,6 @@
 //! Last.fm cover source
 
-// See https://www.last.fm/api/show/album.getInfo
+// See https://www.last.fm/api/show/album.getInfo and https://www.last.fm/api/show/artist.getTopAlbums
 
 use std::{
     collections::{HashMap, HashSet},
@@ -20,27 +20,51 @@ use crate::{
 /// Last.fm cover source
 pub(crate) struct LastFm;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct Response {
-    album: Vec<ResponseAlbum>,
+#[derive(Debug, serde::Deserialize)]
+struct AlbumInfoResponse {
+    album: Option<ResponseAlbum>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize)]
 struct ResponseAlbum {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    artist: Option<String>,
+    #[serde(default)]
     image: Vec<ResponseImage>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, serde::Deserialize)]
+struct TopAlbumsResponse {
+    topalbums: TopAlbums,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TopAlbums {
+    #[serde(default)]
+    album: Vec<TopAlbum>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct TopAlbum {
+    name: String,
+    #[serde(default)]
+    image: Vec<ResponseImage>,
+}
+
+#[derive(Debug, serde::Deserialize)]
 struct ResponseImage {
-    #[serde(rename = "$value", default)]
+    /// Last.fm's JSON API names this field `#text`
+    #[serde(rename = "#text", default)]
     url: String,
-    #[serde(rename = "@size", default)]
+    #[serde(default)]
     size: String,
 }
 
 /// Relevance for Last.fm source
 const LASTFM_RELEVANCE: source::Relevance = source::Relevance {
-    fuzzy: false,
+    score: 0,
     only_front_covers: true,
     unrelated_risk: false,
 };
@@ -63,27 +87,26 @@ static SIZE: LazyLock<HashMap<&str, Metadata<(u32, u32)>>> = LazyLock::new(|| {
     .collect()
 });
 
-#[async_trait::async_trait]
-impl Source for LastFm {
-    async fn search(
+impl LastFm {
+    /// Query `album.getinfo` for the exact artist/album
+    async fn album_info(
         &self,
-        artist: &str,
-        album: &str,
+        nartist: &str,
+        nalbum: &str,
         http: &mut Arc<SourceHttpClient>,
-    ) -> anyhow::Result<Vec<Cover>> {
-        let nartist = normalize(artist);
-        let nalbum = normalize(album);
+    ) -> anyhow::Result<Option<ResponseAlbum>> {
         let url_params = [
             ("method", "album.getinfo"),
             ("api_key", API_KEY),
-            ("artist", &nartist),
-            ("album", &nalbum),
+            ("artist", nartist),
+            ("album", nalbum),
+            ("format", "json"),
         ];
         #[expect(clippy::unwrap_used)] // base URL is absolute
         let search_url =
             Url::parse_with_params("https://ws.audioscrobbler.com/2.0/", url_params).unwrap();
-        let resp: Response = match http.get_xml(search_url).await {
-            Ok(resp) => resp,
+        match http.get_json::<AlbumInfoResponse>(search_url).await {
+            Ok(resp) => Ok(resp.album),
             Err(err)
                 if err
                     .downcast_ref::<reqwest::Error>()
@@ -91,65 +114,129 @@ impl Source for LastFm {
                     .is_some_and(|s| s == StatusCode::NOT_FOUND) =>
             {
                 // API returns 404 for unknown albums
-                return Ok(vec![]);
+                Ok(None)
             }
-            Err(err) => return Err(err),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// `album.getinfo` sometimes returns a matching album with all image sizes blank. Fall back
+    /// to `artist.gettopalbums`, which tends to carry image data more reliably, and pick the
+    /// entry whose name matches the searched album
+    async fn top_album_by_name(
+        &self,
+        artist: &str,
+        nalbum: &str,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Option<ResponseAlbum>> {
+        let url_params = [
+            ("method", "artist.gettopalbums"),
+            ("api_key", API_KEY),
+            ("artist", artist),
+            ("format", "json"),
+        ];
+        #[expect(clippy::unwrap_used)] // base URL is absolute
+        let search_url =
+            Url::parse_with_params("https://ws.audioscrobbler.com/2.0/", url_params).unwrap();
+        let resp: TopAlbumsResponse = http.get_json(search_url).await?;
+        Ok(resp
+            .topalbums
+            .album
+            .into_iter()
+            .find(|a| normalize(&a.name) == nalbum)
+            .map(|a| ResponseAlbum {
+                name: Some(a.name),
+                artist: None,
+                image: a.image,
+            }))
+    }
+
+    /// Whether at least one of `images` has a non-blank URL
+    fn has_usable_image(images: &[ResponseImage]) -> bool {
+        images.iter().any(|i| !i.url.trim().is_empty())
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for LastFm {
+    async fn search(
+        &self,
+        artist: &str,
+        album: &str,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<Cover>> {
+        let nartist = normalize(artist);
+        let nalbum = normalize(album);
+
+        let mut result = self.album_info(&nartist, &nalbum, http).await?;
+        if !result.as_ref().is_some_and(|a| Self::has_usable_image(&a.image)) {
+            result = self.top_album_by_name(artist, &nalbum, http).await?;
+        }
+        let Some(result) = result else {
+            return Ok(vec![]);
+        };
+
+        let relevance = source::Relevance {
+            score: source::match_score(&nartist, result.artist.as_deref(), &nalbum, result.name.as_deref()),
+            ..LASTFM_RELEVANCE
         };
+
+        let Some::<Url>(thumbnail_url) = result
+            .image
+            .iter()
+            .min_by_key(|i| {
+                SIZE.get(i.size.as_str())
+                    .map_or(&u32::MAX, |m| &m.value_hint().0)
+                    .to_owned()
+            })
+            .and_then(|i| i.url.parse().ok())
+        else {
+            return Ok(vec![]);
+        };
+
+        // Keep URLs seen so far to detect fake higher resolution images which reuse the same
+        // link. Only one response's images are used (the fallback replaces a primary response
+        // with no usable image), so only those are compared
         let mut prev_images = HashSet::new();
         let mut results = Vec::new();
-        for (rank, result) in resp.album.into_iter().enumerate() {
-            let Some::<Url>(thumbnail_url) = result
-                .image
-                .iter()
-                .min_by_key(|i| {
-                    SIZE.get(i.size.as_str())
-                        .map_or(&u32::MAX, |m| &m.value_hint().0)
-                        .to_owned()
-                })
-                .and_then(|i| i.url.parse().ok())
-            else {
+        for image in result.image {
+            if image.url.trim().is_empty() {
+                continue;
+            }
+
+            let Some(size_px) = SIZE.get(image.size.as_str()).cloned() else {
                 continue;
             };
-            for image in result.image {
-                if image.url.trim().is_empty() {
-                    continue;
-                }
-
-                let Some(size_px) = SIZE.get(image.size.as_str()).cloned() else {
-                    continue;
-                };
-
-                let url: Url = image
-                    .url
-                    .parse()
-                    .with_context(|| format!("Unable to parse URL {:?}", image.url))?;
-
-                if prev_images.contains(&url) {
-                    continue;
-                }
-                // Keep URL to detect fake higher resolution images which have the same URL
-                prev_images.insert(url.clone());
-
-                let Some(format) = url
-                    .as_str()
-                    .rsplit_once('.')
-                    .and_then(|(_, ext)| Format::from_extension(ext))
-                else {
-                    continue;
-                };
-
-                let cover = Cover {
-                    url,
-                    thumbnail_url: thumbnail_url.clone(),
-                    size_px,
-                    format: Metadata::known(format),
-                    source_name: SourceName::LastFm,
-                    source_http: Arc::clone(http),
-                    relevance: LASTFM_RELEVANCE,
-                    rank,
-                };
-                results.push(cover);
+
+            let url: Url = image
+                .url
+                .parse()
+                .with_context(|| format!("Unable to parse URL {:?}", image.url))?;
+
+            if prev_images.contains(&url) {
+                continue;
             }
+            prev_images.insert(url.clone());
+
+            let Some(format) = url
+                .as_str()
+                .rsplit_once('.')
+                .and_then(|(_, ext)| Format::from_extension(ext))
+            else {
+                continue;
+            };
+
+            let cover = Cover {
+                url,
+                thumbnail_url: thumbnail_url.clone(),
+                size_px,
+                format: Metadata::known(format),
+                source_name: SourceName::LastFm,
+                source_http: Arc::clone(http),
+                relevance,
+                rank: 0,
+            };
+            results.push(cover);
         }
         Ok(results)
     }
@@ -1,21 +1,177 @@
 //! Cover sources
 
+pub(crate) mod cache;
+mod coverartarchive;
+mod deezer;
+mod discogs;
 mod itunes;
+mod lastfm;
+pub(crate) mod subsonic;
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Context as _;
+use reqwest::header::HeaderMap;
 
 use crate::{
-    cl::{CoverSourceName, SearchArgs},
+    cl::{SearchOptions, SourceName},
     cover::Cover,
-    http::Http,
-    source::itunes::Itunes,
+    http::SourceHttpClient,
+    source::{
+        coverartarchive::CoverArtArchive, deezer::Deezer, discogs::Discogs, itunes::Itunes,
+        lastfm::LastFm, subsonic::Subsonic,
+    },
 };
 
+/// How much a cover result can be trusted to actually be the searched release
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Relevance {
+    /// Weighted artist/album similarity score in `[0, 100]`; 100 means an exact match.
+    /// See [`match_score`]
+    pub(crate) score: u8,
+    /// Whether the source only ever returns front covers (`false` means back/inlay art is possible)
+    pub(crate) only_front_covers: bool,
+    /// Whether the source is known to sometimes return art unrelated to the searched release
+    pub(crate) unrelated_risk: bool,
+}
+
+/// Below this [`match_score`], a candidate is not worth keeping
+pub(crate) const MIN_MATCH_SCORE: u8 = 50;
+
+/// [`match_score`] used when a source doesn't return enough text to compare against at all
+const NEUTRAL_MATCH_SCORE: u8 = 70;
+
+/// Weight given to the artist similarity in [`match_score`], the rest goes to the album
+const ARTIST_SCORE_WEIGHT: f64 = 0.4;
+
+/// Weighted artist/album similarity score in `[0, 100]`, computed with Jaro-Winkler over the
+/// `normalize`d strings (artist weighted `ARTIST_SCORE_WEIGHT`, album the rest).
+/// A missing candidate component is excluded from the weighting rather than penalized; if both
+/// are missing, a neutral score is returned
+pub(crate) fn match_score(
+    query_artist: &str,
+    candidate_artist: Option<&str>,
+    query_album: &str,
+    candidate_album: Option<&str>,
+) -> u8 {
+    #[expect(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn to_percent(s: f64) -> u8 {
+        (s * 100.0).round() as u8
+    }
+
+    match (candidate_artist, candidate_album) {
+        (None, None) => NEUTRAL_MATCH_SCORE,
+        (Some(artist), None) => to_percent(jaro_winkler(&normalize(query_artist), &normalize(artist))),
+        (None, Some(album)) => to_percent(jaro_winkler(&normalize(query_album), &normalize(album))),
+        (Some(artist), Some(album)) => {
+            let artist_score = jaro_winkler(&normalize(query_artist), &normalize(artist));
+            let album_score = jaro_winkler(&normalize(query_album), &normalize(album));
+            to_percent(ARTIST_SCORE_WEIGHT.mul_add(artist_score, (1.0 - ARTIST_SCORE_WEIGHT) * album_score))
+        }
+    }
+}
+
+/// Jaro-Winkler similarity between two strings, in `[0.0, 1.0]`
+fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let jaro = jaro_similarity(a, b);
+    if jaro < 0.7 {
+        return jaro;
+    }
+    let prefix_len = a.chars().zip(b.chars()).take_while(|(ca, cb)| ca == cb).take(4).count();
+    #[expect(clippy::cast_precision_loss)]
+    let prefix_bonus = prefix_len as f64 * 0.1;
+    prefix_bonus.mul_add(1.0 - jaro, jaro)
+}
+
+/// Jaro similarity between two strings, in `[0.0, 1.0]`
+fn jaro_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let match_distance = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matches = vec![false; a.len()];
+    let mut b_matches = vec![false; b.len()];
+    let mut matches = 0_usize;
+
+    for (i, &ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(match_distance);
+        let end = (i + match_distance + 1).min(b.len());
+        for (j, matched) in b_matches.iter_mut().enumerate().take(end).skip(start) {
+            if *matched || b[j] != ca {
+                continue;
+            }
+            a_matches[i] = true;
+            *matched = true;
+            matches += 1;
+            break;
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let mut transpositions = 0_usize;
+    let mut k = 0;
+    for (i, &is_match) in a_matches.iter().enumerate() {
+        if !is_match {
+            continue;
+        }
+        while !b_matches[k] {
+            k += 1;
+        }
+        if a[i] != b[k] {
+            transpositions += 1;
+        }
+        k += 1;
+    }
+
+    #[expect(clippy::cast_precision_loss)]
+    {
+        let m = matches as f64;
+        let transpositions = (transpositions / 2) as f64;
+        (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions) / m) / 3.0
+    }
+}
+
+/// A request rate limit to respect for a source
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RateLimit {
+    /// Time window
+    pub(crate) time: Duration,
+    /// Max request count allowed within `time`
+    pub(crate) max_count: u32,
+}
+
 /// Cover source
 #[async_trait::async_trait]
 pub(crate) trait Source: Sync + Send {
-    /// Search for a cover and return results
-    async fn search(&self, query: &SearchArgs, http: &mut Http) -> anyhow::Result<Cover>;
+    /// Search for covers matching an artist/album and return results
+    async fn search(
+        &self,
+        artist: &str,
+        album: &str,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<Cover>>;
+
+    /// Search for covers, preferring a direct lookup by `MusicBrainz` release MBID when one is
+    /// known (eg. from tags). Default implementation ignores the MBID and falls back to `search`
+    async fn search_with_mbid(
+        &self,
+        artist: &str,
+        album: &str,
+        release_mbid: Option<&str>,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<Cover>> {
+        let _ = release_mbid;
+        self.search(artist, album, http).await
+    }
 
     /// Get user-agent to use for all requests
     fn user_agent(&self) -> &'static str {
@@ -26,15 +182,120 @@ pub(crate) trait Source: Sync + Send {
     fn timeout(&self) -> Duration {
         Duration::from_secs(10)
     }
+
+    /// Get an optional rate limit this source's requests must respect
+    fn rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// Get extra headers to send with every request
+    fn common_headers(&self) -> HeaderMap {
+        HeaderMap::new()
+    }
 }
 
-impl From<&CoverSourceName> for Box<dyn Source> {
-    fn from(val: &CoverSourceName) -> Self {
-        match val {
-            CoverSourceName::Deezer => todo!(),
-            CoverSourceName::Discogs => todo!(),
-            CoverSourceName::Itunes => Box::new(Itunes),
-            CoverSourceName::LastFm => todo!(),
-        }
+/// Build a [`Source`] from its name, wiring in any server configuration it needs from
+/// `search_opts` (currently only `Subsonic` requires any)
+pub(crate) fn build_source(name: &SourceName, search_opts: &SearchOptions) -> anyhow::Result<Box<dyn Source>> {
+    Ok(match name {
+        SourceName::CoverArtArchive => Box::new(CoverArtArchive),
+        SourceName::Deezer => Box::new(Deezer),
+        SourceName::Discogs => Box::new(Discogs),
+        SourceName::Itunes => Box::new(Itunes),
+        SourceName::LastFm => Box::new(LastFm),
+        SourceName::Subsonic => Box::new(Subsonic::new(
+            search_opts
+                .subsonic_url
+                .as_deref()
+                .context("--subsonic-url is required to use the subsonic source")?,
+            search_opts
+                .subsonic_user
+                .as_deref()
+                .context("--subsonic-user is required to use the subsonic source")?,
+            search_opts
+                .subsonic_password
+                .as_deref()
+                .context("--subsonic-password is required to use the subsonic source")?,
+        )),
+    })
+}
+
+/// Normalize an artist/album name so the same release compares equal across sources
+pub(crate) fn normalize(s: &str) -> String {
+    s.trim().to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod match_score_tests {
+    use super::{NEUTRAL_MATCH_SCORE, match_score};
+
+    #[test]
+    fn exact_match_scores_100() {
+        assert_eq!(
+            match_score("Pink Floyd", Some("Pink Floyd"), "The Wall", Some("The Wall")),
+            100
+        );
+    }
+
+    #[test]
+    fn mismatched_strings_score_low() {
+        let score = match_score(
+            "Pink Floyd",
+            Some("Metallica"),
+            "The Wall",
+            Some("Master of Puppets"),
+        );
+        assert!(score < 50, "score was {score}");
+    }
+
+    #[test]
+    fn missing_candidate_text_is_neutral() {
+        assert_eq!(match_score("Pink Floyd", None, "The Wall", None), NEUTRAL_MATCH_SCORE);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use super::Source;
+    use crate::{cl::SourceName, http::SourceHttpClient};
+
+    /// Build a throwaway HTTP client for a source under test
+    async fn test_http<S: Source>(source: &S, name: &SourceName) -> Arc<SourceHttpClient> {
+        Arc::new(
+            SourceHttpClient::new(
+                name.as_ref(),
+                source.user_agent(),
+                source.timeout(),
+                source.common_headers(),
+                source.rate_limit(),
+            )
+            .expect("failed to build test HTTP client"),
+        )
+    }
+
+    /// Assert a source returns at least one result for a well known release
+    pub(crate) async fn source_has_results<S: Source>(source: S, name: SourceName) {
+        let mut http = test_http(&source, &name).await;
+        let results = source
+            .search("Pink Floyd", "The Dark Side of the Moon", &mut http)
+            .await
+            .expect("search failed");
+        assert!(!results.is_empty());
+    }
+
+    /// Assert a source returns no results for a release that cannot exist
+    pub(crate) async fn source_no_results<S: Source>(source: S, name: SourceName) {
+        let mut http = test_http(&source, &name).await;
+        let results = source
+            .search(
+                "zzz_does_not_exist_artist_zzz",
+                "zzz_does_not_exist_album_zzz",
+                &mut http,
+            )
+            .await
+            .expect("search failed");
+        assert!(results.is_empty());
     }
 }
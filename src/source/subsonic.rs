@@ -0,0 +1,269 @@
+//! Subsonic-compatible (Navidrome/Airsonic/Gonic) cover source
+//
+// See https://opensubsonic.netlify.app/docs/endpoints/search3/ and
+// https://opensubsonic.netlify.app/docs/endpoints/getcoverart/
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use rand::Rng as _;
+use reqwest::Url;
+
+use crate::{
+    cl::SourceName,
+    cover::{Cover, Format, Metadata},
+    http::SourceHttpClient,
+    source::{self, Source, normalize},
+};
+
+/// Subsonic API version advertised in every request
+const API_VERSION: &str = "1.16.1";
+
+/// Subsonic client id advertised in every request
+const CLIENT_ID: &str = "sacad";
+
+/// Max albums to request per `search3` query
+const SEARCH_ALBUM_COUNT: u32 = 20;
+
+/// Albums to request per `getAlbumList2` page when listing a whole library
+const ALBUM_LIST_PAGE_SIZE: u32 = 500;
+
+/// Subsonic cover source
+pub(crate) struct Subsonic {
+    /// Server base URL, without trailing slash
+    base_url: String,
+    /// Login username, sent as the `u` param
+    user: String,
+    /// Salt used to derive `token`, sent as the `s` param
+    salt: String,
+    /// Token auth credential derived from the password and `salt`, sent as the `t` param
+    token: String,
+}
+
+impl Subsonic {
+    /// Build a new Subsonic source from server config. A random salt is generated once and
+    /// reused for every request made through this instance
+    pub(crate) fn new(base_url: &str, user: &str, password: &str) -> Self {
+        let salt: String = rand::rng()
+            .sample_iter(rand::distr::Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect();
+        let token = format!("{:x}", md5::compute(format!("{password}{salt}")));
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            user: user.to_owned(),
+            salt,
+            token,
+        }
+    }
+
+    /// Build a Subsonic API endpoint URL, with token auth params and `extra_params` added
+    fn endpoint_url(&self, method: &str, extra_params: &[(&str, &str)]) -> anyhow::Result<Url> {
+        let base = format!("{}/rest/{method}.view", self.base_url);
+        let auth_params = [
+            ("u", self.user.as_str()),
+            ("t", self.token.as_str()),
+            ("s", self.salt.as_str()),
+            ("v", API_VERSION),
+            ("c", CLIENT_ID),
+            ("f", "json"),
+        ];
+        Url::parse_with_params(&base, auth_params.iter().chain(extra_params))
+            .with_context(|| format!("Failed to build Subsonic {method} URL"))
+    }
+
+    /// Build thumbnail and full size cover URLs for an album's art, at the requested `size`
+    fn cover_urls(&self, album_id: &str, size: u32) -> anyhow::Result<(Url, Url)> {
+        let size_str = size.to_string();
+        let url = self.endpoint_url("getCoverArt", &[("id", album_id), ("size", &size_str)])?;
+        let thumbnail_url =
+            self.endpoint_url("getCoverArt", &[("id", album_id), ("size", "250")])?;
+        Ok((url, thumbnail_url))
+    }
+
+    /// List every album in the server's library via `getAlbumList2`, paging until exhausted.
+    /// Used to drive a library scan against a remote server instead of the local filesystem
+    pub(crate) async fn list_albums(
+        &self,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<RemoteAlbum>> {
+        let mut albums = Vec::new();
+        let mut offset = 0_u32;
+        loop {
+            let size_str = ALBUM_LIST_PAGE_SIZE.to_string();
+            let offset_str = offset.to_string();
+            let url = self.endpoint_url(
+                "getAlbumList2",
+                &[
+                    ("type", "alphabeticalByName"),
+                    ("size", &size_str),
+                    ("offset", &offset_str),
+                ],
+            )?;
+            let resp: AlbumListResponse = http.get_json(url).await?;
+            let page_len = resp.subsonic_response.album_list2.album.len();
+            albums.extend(
+                resp.subsonic_response
+                    .album_list2
+                    .album
+                    .into_iter()
+                    .map(|a| RemoteAlbum {
+                        artist: a.artist,
+                        album: a.name,
+                    }),
+            );
+
+            if page_len < ALBUM_LIST_PAGE_SIZE as usize {
+                break;
+            }
+            offset += ALBUM_LIST_PAGE_SIZE;
+        }
+        Ok(albums)
+    }
+}
+
+/// One album entry returned by a remote library scan
+#[derive(Debug, Clone)]
+pub struct RemoteAlbum {
+    /// Artist name
+    pub artist: String,
+    /// Album name
+    pub album: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SearchResponse {
+    #[serde(rename = "subsonic-response")]
+    subsonic_response: SearchResponseBody,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct SearchResponseBody {
+    #[serde(rename = "searchResult3", default)]
+    search_result3: SearchResult3,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct SearchResult3 {
+    #[serde(default)]
+    album: Vec<AlbumResult>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlbumResult {
+    id: String,
+    name: String,
+    artist: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlbumListResponse {
+    #[serde(rename = "subsonic-response")]
+    subsonic_response: AlbumListResponseBody,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlbumListResponseBody {
+    #[serde(rename = "albumList2")]
+    album_list2: AlbumList2,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlbumList2 {
+    #[serde(default)]
+    album: Vec<AlbumListEntry>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AlbumListEntry {
+    artist: String,
+    name: String,
+}
+
+#[async_trait::async_trait]
+impl Source for Subsonic {
+    async fn search(
+        &self,
+        artist: &str,
+        album: &str,
+        http: &mut Arc<SourceHttpClient>,
+    ) -> anyhow::Result<Vec<Cover>> {
+        let nartist = normalize(artist);
+        let nalbum = normalize(album);
+
+        let query = format!("{artist} {album}");
+        let count_str = SEARCH_ALBUM_COUNT.to_string();
+        let search_url = self.endpoint_url(
+            "search3",
+            &[
+                ("query", query.as_str()),
+                ("albumCount", count_str.as_str()),
+                ("artistCount", "0"),
+                ("songCount", "0"),
+            ],
+        )?;
+        let resp: SearchResponse = http.get_json(search_url).await?;
+
+        let mut results = Vec::new();
+        for (rank, result) in resp
+            .subsonic_response
+            .search_result3
+            .album
+            .into_iter()
+            .enumerate()
+        {
+            let (url, thumbnail_url) = self.cover_urls(&result.id, 1200)?;
+            let relevance = source::Relevance {
+                score: source::match_score(&nartist, result.artist.as_deref(), &nalbum, Some(&result.name)),
+                only_front_covers: true,
+                unrelated_risk: false,
+            };
+            results.push(Cover {
+                url,
+                thumbnail_url,
+                size_px: Metadata::uncertain((1200, 1200)),
+                format: Metadata::uncertain(Format::Jpeg),
+                source_name: SourceName::Subsonic,
+                source_http: Arc::clone(http),
+                relevance,
+                rank,
+            });
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_url_includes_auth_and_extra_params() {
+        let source = Subsonic::new("https://music.example.com/", "alice", "hunter2");
+        let url = source
+            .endpoint_url("search3", &[("query", "floyd")])
+            .expect("failed to build URL");
+        assert_eq!(url.host_str(), Some("music.example.com"));
+        assert_eq!(url.path(), "/rest/search3.view");
+        let pairs: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(pairs.get("u"), Some(&"alice".to_owned()));
+        assert_eq!(pairs.get("s"), Some(&source.salt));
+        assert_eq!(pairs.get("t"), Some(&source.token));
+        assert_eq!(pairs.get("query"), Some(&"floyd".to_owned()));
+    }
+
+    #[test]
+    fn cover_urls_use_requested_size() {
+        let source = Subsonic::new("https://music.example.com", "alice", "hunter2");
+        let (url, thumbnail_url) = source.cover_urls("42", 1200).expect("failed to build URLs");
+        let size = |url: &Url| {
+            url.query_pairs()
+                .find(|(k, _)| k == "size")
+                .map(|(_, v)| v.into_owned())
+        };
+        assert_eq!(size(&url), Some("1200".to_owned()));
+        assert_eq!(size(&thumbnail_url), Some("250".to_owned()));
+    }
+}
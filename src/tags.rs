@@ -20,10 +20,13 @@ pub struct Tags {
     pub album: String,
     /// If requested, whether file has embedded cover or not
     pub has_embedded_cover: Option<bool>,
+    /// `MusicBrainz` release MBID, if tagged
+    pub release_mbid: Option<String>,
 }
 
 const ARTIST_KEYS: [tag::ItemKey; 2] = [tag::ItemKey::TrackArtist, tag::ItemKey::AlbumArtist];
 const ALBUM_KEYS: [tag::ItemKey; 1] = [tag::ItemKey::AlbumTitle];
+const RELEASE_MBID_KEYS: [tag::ItemKey; 1] = [tag::ItemKey::MusicBrainzReleaseId];
 
 fn extract_tag<'a>(tags: &'a tag::Tag, keys: &'_ [tag::ItemKey]) -> Option<&'a str> {
     let mut value = None;
@@ -62,32 +65,58 @@ pub fn read_metadata(file_paths: &[PathBuf], probe_embedded_cover: bool) -> Opti
                 artist: extract_tag(tags, &ARTIST_KEYS)?.to_owned(),
                 album: extract_tag(tags, &ALBUM_KEYS)?.to_owned(),
                 has_embedded_cover,
+                release_mbid: extract_tag(tags, &RELEASE_MBID_KEYS).map(str::to_owned),
             });
         }
     }
     None
 }
 
-/// Embed front cover into all given files
-pub fn embed_cover(img_path: &Path, audio_filepaths: Vec<PathBuf>) -> anyhow::Result<()> {
-    let mut img_file = fs::File::open(img_path)
+/// Best-effort MIME type detection from raw image magic bytes
+fn detect_mime_type(data: &[u8]) -> picture::MimeType {
+    match data {
+        [0xff, 0xd8, 0xff, ..] => picture::MimeType::Jpeg,
+        [0x89, b'P', b'N', b'G', ..] => picture::MimeType::Png,
+        _ => picture::MimeType::Unknown("application/octet-stream".to_owned()),
+    }
+}
+
+/// Embed front cover into every tag format present in each given file (a file with both, say, an
+/// ID3v2 and an APE tag gets the picture in both, so every player sees it regardless of which
+/// container it reads). `mime_type` defaults to a guess from `img_path`'s own content when not
+/// given; `description` is stored alongside the picture (eg. in ID3 APIC frames)
+pub fn embed_cover(
+    img_path: &Path,
+    mime_type: Option<picture::MimeType>,
+    description: Option<&str>,
+    audio_filepaths: Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let img_data = fs::read(img_path)
         .with_context(|| format!("Failed to read image from {img_path:?}"))?;
-    let mut picture =
-        picture::Picture::from_reader(&mut img_file).context("Failed to load image")?;
-    picture.set_pic_type(picture::PictureType::CoverFront);
+    let mime_type = mime_type.unwrap_or_else(|| detect_mime_type(&img_data));
+    let picture = picture::Picture::new_unchecked(
+        picture::PictureType::CoverFront,
+        Some(mime_type),
+        description.map(str::to_owned),
+        img_data,
+    );
 
     for audio_filepath in audio_filepaths {
         let mut file = lofty::read_from_path(&audio_filepath)
             .with_context(|| format!("Failed to load tags from {audio_filepath:?}"))?;
-        if let Some(tag_type) = usable_tag_type(&file) {
-            let tags = file
-                .tag_mut(tag_type)
-                .ok_or_else(|| anyhow::anyhow!("Tags have disappeared from {audio_filepath:?}"))?;
+        let tag_types: Vec<_> = file.tags().iter().map(tag::Tag::tag_type).collect();
+        if tag_types.is_empty() {
+            continue;
+        }
+        for tag_type in tag_types {
+            let Some(tags) = file.tag_mut(tag_type) else {
+                continue;
+            };
             tags.remove_picture_type(picture::PictureType::CoverFront);
             tags.push_picture(picture.clone());
-            file.save_to_path(&audio_filepath, lofty::config::WriteOptions::default())
-                .with_context(|| format!("Failed to write tags to {audio_filepath:?}"))?;
         }
+        file.save_to_path(&audio_filepath, lofty::config::WriteOptions::default())
+            .with_context(|| format!("Failed to write tags to {audio_filepath:?}"))?;
     }
 
     Ok(())
@@ -19,6 +19,9 @@ pub struct Stats {
     pub audio_dirs: AtomicUsize,
     /// Count of covers needing search & download
     pub missing_covers: AtomicUsize,
+    /// Count of albums that already had a cover, including those re-fetched anyway because of
+    /// `--ignore-existing`
+    pub already_has_cover: AtomicUsize,
     /// Count of covers successfully downloaded
     pub done: AtomicUsize,
     /// Count of searches that yielded no result